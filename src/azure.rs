@@ -0,0 +1,445 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const AZURE_MANAGEMENT_API: &str = "https://management.azure.com";
+const AZURE_DNS_API_VERSION: &str = "2018-05-01";
+
+// 全局的 AAD access token 缓存: tenant_id/client_id -> (access_token, 过期时间)
+static AZURE_TOKEN_CACHE: LazyLock<Mutex<HashMap<String, (String, Instant)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// ========== Azure DNS 相关结构 ==========
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzureZoneListResponse {
+    value: Vec<AzureZone>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzureZone {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct AzureRecordSet {
+    /// 记录集的ARM资源ID
+    #[serde(default, skip_serializing)]
+    id: Option<String>,
+    #[serde(default, skip_serializing)]
+    etag: Option<String>,
+    properties: AzureRecordSetProperties,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct AzureRecordSetProperties {
+    #[serde(rename = "TTL")]
+    ttl: u32,
+    #[serde(rename = "ARecords", skip_serializing_if = "Option::is_none")]
+    a_records: Option<Vec<AzureARecord>>,
+    #[serde(rename = "AAAARecords", skip_serializing_if = "Option::is_none")]
+    aaaa_records: Option<Vec<AzureAaaaRecord>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct AzureARecord {
+    #[serde(rename = "ipv4Address")]
+    ipv4_address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct AzureAaaaRecord {
+    #[serde(rename = "ipv6Address")]
+    ipv6_address: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzureErrorResponse {
+    error: AzureError,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzureError {
+    code: String,
+    message: String,
+}
+
+// ========== Azure DNS Provider 实现 ==========
+
+/// AAD 应用（服务主体）凭据
+#[derive(Clone)]
+pub struct AzureCredential {
+    pub tenant_id: String,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+pub struct AzureProvider {
+    api: String,
+    credential: AzureCredential,
+    subscription_id: String,
+    resource_group: String,
+    zone: Option<String>,
+    record_name: String,
+    record_type: &'static str,
+    /// get_record读取到的记录集ETag，修改时用于If-Match
+    etag: Mutex<Option<String>>,
+}
+
+impl AzureProvider {
+    pub fn new(
+        credential: AzureCredential,
+        subscription_id: String,
+        resource_group: String,
+        record_name: String,
+    ) -> Self {
+        AzureProvider {
+            api: AZURE_MANAGEMENT_API.to_string(),
+            credential,
+            subscription_id,
+            resource_group,
+            zone: None,
+            record_name,
+            record_type: "A",
+            etag: Mutex::new(None),
+        }
+    }
+
+    /// 指定DNS zone名称，未指定时从资源组的zone列表中按最长后缀匹配
+    pub fn zone(mut self, zone: Option<String>) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// 读写的记录集类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 通过client-credentials流程获取access token，优先从缓存读取，过期前60秒刷新
+    fn access_token(&self) -> Result<String, Error> {
+        let cache_key = format!(
+            "{}/{}",
+            self.credential.tenant_id, self.credential.client_id
+        );
+        {
+            let cache = AZURE_TOKEN_CACHE.lock().unwrap();
+            if let Some((token, expires_at)) = cache.get(&cache_key)
+                && Instant::now() + Duration::from_secs(60) < *expires_at
+            {
+                debug!("Using cached azure access token for {}", cache_key);
+                return Ok(token.clone());
+            }
+        }
+
+        let client = crate::http_client()?;
+        let response = client
+            .post(format!(
+                "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                self.credential.tenant_id
+            ))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.credential.client_id.as_str()),
+                ("client_secret", self.credential.client_secret.as_str()),
+                ("scope", "https://management.azure.com/.default"),
+            ])
+            .send()
+            .map_err(|e| anyhow!("Failed to request azure access token: {}", e))?;
+        let status = response.status();
+        let text = response.text()?;
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Failed to obtain azure access token ({}): {}",
+                status,
+                text
+            ));
+        }
+        let token: TokenResponse = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse azure token response: {}", e))?;
+        debug!(
+            "Obtained azure access token for {}, expires in {}s",
+            cache_key, token.expires_in
+        );
+
+        AZURE_TOKEN_CACHE.lock().unwrap().insert(
+            cache_key,
+            (
+                token.access_token.clone(),
+                Instant::now() + Duration::from_secs(token.expires_in),
+            ),
+        );
+        Ok(token.access_token)
+    }
+
+    fn zones_url(&self) -> String {
+        format!(
+            "{}/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/dnsZones",
+            self.api, self.subscription_id, self.resource_group
+        )
+    }
+
+    /// 返回(zone名称, 相对记录名)
+    fn get_zone(&self, token: &str) -> Result<(String, String), Error> {
+        if let Some(zone) = &self.zone {
+            let name = relative_name(&self.record_name, zone)
+                .ok_or_else(|| anyhow!("Domain {} is not in zone {}", self.record_name, zone))?;
+            return Ok((zone.clone(), name));
+        }
+
+        let client = crate::http_client()?;
+        let response = client
+            .get(self.zones_url())
+            .query(&[("api-version", AZURE_DNS_API_VERSION)])
+            .bearer_auth(token)
+            .send()
+            .map_err(|e| anyhow!("Failed to query zone list: {}", e))?;
+        let text = Self::check_response(response)?;
+        let zones: AzureZoneListResponse = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse zone list response: {}", e))?;
+        zones
+            .value
+            .into_iter()
+            .filter_map(|zone| relative_name(&self.record_name, &zone.name).map(|n| (zone.name, n)))
+            .max_by_key(|(zone, _)| zone.len())
+            .ok_or_else(|| anyhow!("No zone found for domain: {}", self.record_name))
+    }
+
+    fn record_set_url(&self, zone: &str, record_type: &str, name: &str) -> String {
+        format!(
+            "{}/{zone}/{record_type}/{name}?api-version={AZURE_DNS_API_VERSION}",
+            self.zones_url()
+        )
+    }
+
+    /// 检查HTTP状态码，非2xx时解析ARM的错误信息
+    fn check_response(response: reqwest::blocking::Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else if status == StatusCode::PRECONDITION_FAILED {
+            Err(anyhow!(
+                "Azure DNS record set was modified concurrently (ETag mismatch)"
+            ))
+        } else {
+            let message = serde_json::from_str::<AzureErrorResponse>(&text)
+                .map(|e| format!("{}: {}", e.error.code, e.error.message))
+                .unwrap_or(text);
+            Err(anyhow!("Azure DNS API error ({}): {}", status, message))
+        }
+    }
+
+    /// PUT记录集，创建时使用If-None-Match仅允许创建，修改时使用读取到的ETag进行乐观并发控制
+    fn put_record_set(&self, current_ip: &str, create: bool) -> Result<(), Error> {
+        let token = self.access_token()?;
+        let (zone, name) = self.get_zone(&token)?;
+        let record_type = get_record_type(current_ip);
+
+        let mut properties = AzureRecordSetProperties {
+            ttl: 60,
+            ..Default::default()
+        };
+        if record_type == "AAAA" {
+            properties.aaaa_records = Some(vec![AzureAaaaRecord {
+                ipv6_address: current_ip.to_string(),
+            }]);
+        } else {
+            properties.a_records = Some(vec![AzureARecord {
+                ipv4_address: current_ip.to_string(),
+            }]);
+        }
+
+        let client = crate::http_client()?;
+        let mut request = client
+            .put(self.record_set_url(&zone, record_type, &name))
+            .bearer_auth(&token)
+            .json(&AzureRecordSet {
+                id: None,
+                etag: None,
+                properties,
+            });
+        if create {
+            request = request.header("If-None-Match", "*");
+        } else if let Some(etag) = self.etag.lock().unwrap().as_deref() {
+            request = request.header("If-Match", etag);
+        }
+        let text = Self::check_response(request.send()?)?;
+        debug!("azure put record set result: {text}");
+        Ok(())
+    }
+}
+
+impl DnsProvider for AzureProvider {
+    /// 获取DNS记录，只查询record_type类型的记录集，DnsRecord.id 为记录集的资源ID
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let token = self.access_token()?;
+        let (zone, name) = self.get_zone(&token)?;
+        let client = crate::http_client()?;
+
+        let res = client
+            .get(self.record_set_url(&zone, self.record_type, &name))
+            .bearer_auth(&token)
+            .send()?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let text = Self::check_response(res)?;
+        let record_set: AzureRecordSet = match serde_json::from_str(&text) {
+            Ok(record_set) => record_set,
+            Err(err) => {
+                warn!("error parse azure result: {text}");
+                return Err(anyhow!(err));
+            }
+        };
+
+        let value = match self.record_type {
+            "AAAA" => record_set
+                .properties
+                .aaaa_records
+                .as_ref()
+                .and_then(|records| records.first())
+                .map(|r| r.ipv6_address.clone()),
+            _ => record_set
+                .properties
+                .a_records
+                .as_ref()
+                .and_then(|records| records.first())
+                .map(|r| r.ipv4_address.clone()),
+        };
+        // 记录集存在但没有地址时同样按修改处理
+        info!("current azure record set is {:?}", record_set);
+        *self.etag.lock().unwrap() = record_set.etag;
+        Ok(Some(DnsRecord {
+            id: record_set.id.unwrap_or_default(),
+            name,
+            value: value.unwrap_or_default(),
+            record_type: self.record_type.to_string(),
+        }))
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, _record: &DnsRecord) -> Result<(), Error> {
+        self.put_record_set(current_ip, false)
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        self.put_record_set(current_ip, true)
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Azure DNS配置
+#[derive(Deserialize, Debug)]
+pub struct AzureConfig {
+    /// Azure AD租户ID
+    pub azure_tenant_id: String,
+    /// Azure AD应用(client) ID
+    pub azure_client_id: String,
+    /// Azure AD应用密钥
+    pub azure_client_secret: String,
+    /// Azure订阅ID
+    pub azure_subscription_id: String,
+    /// DNS zone所在的资源组
+    pub azure_resource_group: String,
+    /// DNS zone名称，未设置时从资源组的zone列表中按最长后缀匹配
+    #[serde(default)]
+    pub azure_zone: Option<String>,
+}
+
+impl ProviderConfig for AzureConfig {
+    const NAME: &'static str = "azure";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        let credential = AzureCredential {
+            tenant_id: self.azure_tenant_id,
+            client_id: self.azure_client_id,
+            client_secret: self.azure_client_secret,
+        };
+        let provider = AzureProvider::new(
+            credential,
+            self.azure_subscription_id,
+            self.azure_resource_group,
+            context.domain.to_string(),
+        )
+        .zone(self.azure_zone)
+        .record_type(get_record_type(context.current_ip));
+        Ok(Box::new(provider))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        let (address, handle) = serve_http(vec![
+            r#"{"id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/dnsZones/example.com/AAAA/home",
+                "etag": "etag-1", "properties": {"TTL": 60, "AAAARecords": [{"ipv6Address": "2001:db8::1"}]}}"#,
+            "{}",
+            r#"{"properties": {"TTL": 60, "AAAARecords": [{"ipv6Address": "2001:db8::2"}]}}"#,
+            "{}",
+        ]);
+        let credential = AzureCredential {
+            tenant_id: "tenant".to_string(),
+            client_id: "ddns-test".to_string(),
+            client_secret: "secret".to_string(),
+        };
+        // 使用缓存的access token，不需要请求AAD
+        AZURE_TOKEN_CACHE.lock().unwrap().insert(
+            "tenant/ddns-test".to_string(),
+            (
+                "token".to_string(),
+                Instant::now() + Duration::from_secs(3600),
+            ),
+        );
+        let mut provider = AzureProvider::new(
+            credential,
+            "sub".to_string(),
+            "rg".to_string(),
+            "home.example.com".to_string(),
+        )
+        .zone(Some("example.com".to_string()))
+        .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // 只读取和更新AAAA记录集，不创建或删除A记录集
+        let path = "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.Network/dnsZones/example.com/AAAA/home";
+        let result = provider.update_dns_record("2001:db8::2").unwrap();
+        assert!(matches!(
+            result,
+            DnsUpdateResult::Changed { old_ip, record_id: Some(id) }
+                if old_ip == "2001:db8::1" && id == path
+        ));
+
+        // 响应中没有ETag时不带If-Match
+        provider.update_dns_record("2001:db8::3").unwrap();
+        let requests = handle.join().unwrap();
+        assert!(requests[0].0.starts_with(&format!("GET {path}?")));
+        assert!(requests[1].0.starts_with(&format!("PUT {path}?")));
+        assert!(requests[1].0.to_lowercase().contains("if-match: etag-1"));
+        assert!(requests[3].0.starts_with(&format!("PUT {path}?")));
+        assert!(!requests[3].0.to_lowercase().contains("if-match"));
+        assert!(!requests[3].0.to_lowercase().contains("if-none-match"));
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Error, anyhow};
+use socket2::{Domain, Socket, Type};
+use std::cell::RefCell;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+// ========== 出站连接绑定 ==========
+
+/// 出站连接绑定的网卡或源地址，用于多WAN口时指定从哪条线路发出请求
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bind {
+    /// 网卡名称 (如 "pppoe-wan2")，通过 SO_BINDTODEVICE 绑定，仅支持Linux
+    pub interface: Option<String>,
+    /// 源地址，需为本机网卡上的地址
+    pub address: Option<IpAddr>,
+}
+
+thread_local! {
+    // 当前线程中provider发起API请求时使用的绑定
+    static PROVIDER_BIND: RefCell<Bind> = RefCell::new(Bind::default());
+    // 当前线程中最近一次创建的HTTP客户端及其绑定，绑定不变时复用
+    static PROVIDER_CLIENT: RefCell<Option<(Bind, reqwest::blocking::Client)>> =
+        const { RefCell::new(None) };
+}
+
+impl Bind {
+    /// 是否未绑定网卡和源地址
+    pub fn is_unbound(&self) -> bool {
+        self.interface.is_none() && self.address.is_none()
+    }
+
+    /// 校验配置，在加载配置文件时调用
+    pub fn validate(&self) -> Result<(), Error> {
+        match &self.interface {
+            Some(interface) if interface.is_empty() => {
+                Err(anyhow!("bind_interface cannot be empty"))
+            }
+            Some(_) if !cfg!(target_os = "linux") => Err(anyhow!(
+                "bind_interface is only supported on Linux, use bind_address instead"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 为HTTP客户端设置绑定的网卡和源地址
+    pub fn client_builder(
+        &self,
+        builder: reqwest::blocking::ClientBuilder,
+    ) -> reqwest::blocking::ClientBuilder {
+        let builder = builder.local_address(self.address);
+        #[cfg(target_os = "linux")]
+        if let Some(interface) = &self.interface {
+            return builder.interface(interface);
+        }
+        builder
+    }
+
+    /// 创建绑定到网卡和源地址的UDP socket，未设置源地址时绑定到对应IP类型的任意地址
+    pub fn udp_socket(&self, ipv6: bool) -> Result<UdpSocket, Error> {
+        let address: SocketAddr = match self.address {
+            Some(address) if address.is_ipv6() != ipv6 => {
+                return Err(anyhow!(
+                    "bind_address {address} cannot be used for {} requests",
+                    if ipv6 { "IPv6" } else { "IPv4" }
+                ));
+            }
+            Some(address) => (address, 0).into(),
+            None if ipv6 => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            None => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = Socket::new(Domain::for_address(address), Type::DGRAM, None)?;
+        #[cfg(target_os = "linux")]
+        if let Some(interface) = &self.interface {
+            socket
+                .bind_device(Some(interface.as_bytes()))
+                .map_err(|e| anyhow!("failed to bind to interface {interface}: {e}"))?;
+        }
+        socket
+            .bind(&address.into())
+            .map_err(|e| anyhow!("failed to bind to {address}: {e}"))?;
+        Ok(socket.into())
+    }
+}
+
+/// 离开作用域时 (包括f中发生panic) 恢复之前的绑定
+struct ProviderBindGuard {
+    previous: Option<Bind>,
+}
+
+impl Drop for ProviderBindGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            PROVIDER_BIND.set(previous);
+        }
+    }
+}
+
+/// 在f执行期间，当前线程中provider发起的API请求使用bind绑定的网卡和源地址
+pub fn with_provider_bind<R>(bind: &Bind, f: impl FnOnce() -> R) -> R {
+    let _guard = ProviderBindGuard {
+        previous: Some(PROVIDER_BIND.replace(bind.clone())),
+    };
+    f()
+}
+
+/// provider使用的HTTP客户端，按with_provider_bind设置的绑定发起请求
+pub(crate) fn http_client() -> Result<reqwest::blocking::Client, Error> {
+    let bind = PROVIDER_BIND.with_borrow(Clone::clone);
+    PROVIDER_CLIENT.with_borrow_mut(|cached| {
+        if let Some((cached_bind, client)) = cached
+            && *cached_bind == bind
+        {
+            return Ok(client.clone());
+        }
+        let client = bind
+            .client_builder(reqwest::blocking::Client::builder())
+            .build()
+            .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))?;
+        *cached = Some((bind, client.clone()));
+        Ok(client)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind() {
+        let bind = Bind {
+            interface: None,
+            address: Some("127.0.0.1".parse().unwrap()),
+        };
+        let socket = bind.udp_socket(false).unwrap();
+        assert_eq!(socket.local_addr().unwrap().ip(), bind.address.unwrap());
+        assert!(bind.udp_socket(true).is_err());
+        assert!(
+            Bind::default()
+                .udp_socket(false)
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .ip()
+                .is_unspecified()
+        );
+
+        // 作用域结束后恢复之前的绑定
+        with_provider_bind(&bind, || {
+            assert_eq!(PROVIDER_BIND.with_borrow(Clone::clone), bind);
+            with_provider_bind(&Bind::default(), || {
+                assert!(PROVIDER_BIND.with_borrow(Bind::is_unbound));
+            });
+            assert_eq!(PROVIDER_BIND.with_borrow(Clone::clone), bind);
+        });
+        assert!(PROVIDER_BIND.with_borrow(Bind::is_unbound));
+
+        // f中发生panic时同样恢复
+        let result = std::panic::catch_unwind(|| {
+            with_provider_bind(&bind, || panic!("provider panicked"));
+        });
+        assert!(result.is_err());
+        assert!(PROVIDER_BIND.with_borrow(Bind::is_unbound));
+
+        // 按当前绑定创建并缓存客户端
+        with_provider_bind(&bind, || http_client().unwrap());
+        let cached = PROVIDER_CLIENT.with_borrow(|cached| cached.as_ref().map(|(b, _)| b.clone()));
+        assert_eq!(cached, Some(bind));
+
+        let empty = Bind {
+            interface: Some(String::new()),
+            address: None,
+        };
+        assert!(empty.validate().is_err());
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::{Error, anyhow};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
 use std::sync::Mutex;
 use std::{collections::HashMap, sync::LazyLock};
 
-use crate::{DnsProvider, DnsRecord};
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type};
 
 // 全局的 Cloudflare Zone 缓存: api_token -> domain -> zone_id
 static CLOUDFLARE_ZONE_CACHE: LazyLock<Mutex<HashMap<String, HashMap<String, String>>>> =
@@ -121,7 +121,7 @@ impl CloudflareProvider {
 
         // 缓存未命中，调用API查询
         debug!("Querying zone_id for domain: {}", zone_name);
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
 
         let url = format!(
             "https://api.cloudflare.com/client/v4/zones?name={}",
@@ -166,22 +166,13 @@ impl CloudflareProvider {
 
         Ok(zone_id)
     }
-
-    /// 判断IP地址类型，返回对应的记录类型
-    fn get_record_type(ip: &str) -> &'static str {
-        match ip.parse::<IpAddr>() {
-            Ok(IpAddr::V4(_)) => "A",
-            Ok(IpAddr::V6(_)) => "AAAA",
-            Err(_) => "A", // 默认使用A记录
-        }
-    }
 }
 
 impl DnsProvider for CloudflareProvider {
     /// 获取DNS记录
     fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
         let zone_id = self.get_zone_id()?;
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let url = format!(
             "https://api.cloudflare.com/client/v4/zones/{}/dns_records?name={}&type=CNAME,A,AAAA",
             zone_id, self.record_name
@@ -230,14 +221,14 @@ impl DnsProvider for CloudflareProvider {
     /// 修改DNS记录
     fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
         let zone_id = self.get_zone_id()?;
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let url = format!(
             "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}",
             zone_id, record.id
         );
 
         let update_request = CloudflareUpdateRequest {
-            record_type: Self::get_record_type(current_ip).to_string(),
+            record_type: get_record_type(current_ip).to_string(),
             name: self.record_name.clone(),
             content: current_ip.to_string(),
             ttl: 1, // 自动TTL
@@ -278,14 +269,14 @@ impl DnsProvider for CloudflareProvider {
     /// 添加DNS记录
     fn add_record(&self, current_ip: &str) -> Result<(), Error> {
         let zone_id = self.get_zone_id()?;
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let url = format!(
             "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
             zone_id
         );
 
         let create_request = CloudflareCreateRequest {
-            record_type: Self::get_record_type(current_ip).to_string(),
+            record_type: get_record_type(current_ip).to_string(),
             name: self.record_name.clone(),
             content: current_ip.to_string(),
             ttl: 1, // 自动TTL
@@ -323,3 +314,23 @@ impl DnsProvider for CloudflareProvider {
         }
     }
 }
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Cloudflare配置
+#[derive(Deserialize, Debug)]
+pub struct CloudflareConfig {
+    /// Cloudflare API Token
+    pub cloudflare_token: String,
+}
+
+impl ProviderConfig for CloudflareConfig {
+    const NAME: &'static str = "cloudflare";
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(CloudflareProvider::new(
+            self.cloudflare_token,
+            context.domain.to_string(),
+        )))
+    }
+}
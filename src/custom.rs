@@ -0,0 +1,581 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use minijinja::{Environment, UndefinedBehavior};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::schedule::deserialize_optional_duration;
+use crate::{DnsProvider, DnsRecord, get_record_type};
+
+/// 外部程序模式下未配置timeout时等待程序结束的时间
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ========== Custom 相关结构 ==========
+
+/// custom provider 的配置
+/// HTTP模板模式（get / add / modify）与外部程序模式（command）二选一
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CustomConfig {
+    /// 外部程序路径，设置后通过 stdin/stdout 上的JSON协议完成查询和更新
+    #[serde(default)]
+    command: Option<String>,
+
+    /// 外部程序的参数
+    #[serde(default)]
+    args: Vec<String>,
+
+    /// 等待外部程序结束的超时时间 (如 "10s")，默认为30秒，超时后结束进程
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    timeout: Option<Duration>,
+
+    /// 自定义变量（如API Token），模板中通过 vars.xxx 引用，外部程序模式下随请求发送
+    #[serde(default)]
+    vars: BTreeMap<String, String>,
+
+    /// 查询记录的请求
+    #[serde(default)]
+    get: Option<CustomRequest>,
+
+    /// 修改记录的请求，未设置时使用add
+    #[serde(default)]
+    modify: Option<CustomRequest>,
+
+    /// 添加记录的请求
+    #[serde(default)]
+    add: Option<CustomRequest>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct CustomRequest {
+    /// HTTP方法，默认为GET
+    #[serde(default = "default_method")]
+    method: String,
+
+    /// URL模板
+    url: String,
+
+    /// 请求头模板
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+
+    /// 请求体模板
+    #[serde(default)]
+    body: Option<String>,
+
+    /// 视为成功的HTTP状态码，为空时接受所有2xx
+    #[serde(default)]
+    success_status: Vec<u16>,
+
+    /// 判断成功的JSONPath，必须至少匹配到一个值
+    #[serde(default)]
+    success_path: Option<String>,
+
+    /// success_path 匹配到的第一个值必须等于该值（数字、布尔值按字符串比较）
+    #[serde(default)]
+    success_value: Option<String>,
+
+    /// 提取记录id的JSONPath（仅get使用）
+    #[serde(default)]
+    record_id: Option<String>,
+
+    /// 提取记录值的JSONPath（仅get使用，必须设置）
+    #[serde(default)]
+    record_value: Option<String>,
+
+    /// 提取记录类型的JSONPath（仅get使用，未设置时根据记录值判断）
+    #[serde(default)]
+    record_type: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// 模板渲染上下文，同时也是外部程序模式下写入stdin的请求
+#[derive(Serialize, Debug)]
+struct CustomContext<'a> {
+    /// "get"、"modify" 或 "add"
+    action: &'static str,
+    domain: &'a str,
+    /// 新的IP（get时为null）
+    ip: Option<&'a str>,
+    /// 新IP对应的记录类型（get时为null）
+    record_type: Option<&'static str>,
+    /// 当前记录（仅modify时存在）
+    record: Option<CustomRecord>,
+    vars: &'a BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CustomRecord {
+    #[serde(default)]
+    id: String,
+    value: String,
+    #[serde(rename = "type", default)]
+    record_type: Option<String>,
+}
+
+/// 外部程序写到stdout的响应
+#[derive(Deserialize, Debug, Default)]
+struct CustomCommandResponse {
+    #[serde(default)]
+    record: Option<CustomRecord>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl CustomConfig {
+    /// 检查配置是否完整，并预先编译模板和JSONPath，避免运行时才发现错误
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(anyhow!("custom provider: timeout must be greater than 0"));
+        }
+        if self.command.is_some() {
+            if self.get.is_some() || self.modify.is_some() || self.add.is_some() {
+                return Err(anyhow!(
+                    "custom provider: command and get/modify/add are mutually exclusive"
+                ));
+            }
+            return Ok(());
+        }
+
+        let get = self
+            .get
+            .as_ref()
+            .ok_or_else(|| anyhow!("custom provider: neither command nor get is configured"))?;
+        if get.record_value.is_none() {
+            return Err(anyhow!("custom provider: get.record_value is required"));
+        }
+        if self.add.is_none() {
+            return Err(anyhow!("custom provider: add is required"));
+        }
+
+        let env = template_env();
+        for (action, request) in [
+            ("get", &self.get),
+            ("modify", &self.modify),
+            ("add", &self.add),
+        ] {
+            let Some(request) = request else {
+                continue;
+            };
+            Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())
+                .map_err(|_| anyhow!("custom provider: invalid {action}.method"))?;
+            let templates = std::iter::once(&request.url)
+                .chain(request.headers.values())
+                .chain(request.body.iter());
+            for template in templates {
+                env.template_from_str(template)
+                    .map_err(|e| anyhow!("custom provider: invalid template in {action}: {e}"))?;
+            }
+            let paths = [
+                &request.success_path,
+                &request.record_id,
+                &request.record_value,
+                &request.record_type,
+            ];
+            for path in paths.into_iter().flatten() {
+                JsonPath::parse(path)
+                    .map_err(|e| anyhow!("custom provider: invalid JSONPath in {action}: {e}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ========== Custom Provider 实现 ==========
+
+pub struct CustomProvider {
+    config: CustomConfig,
+    record_name: String,
+}
+
+impl CustomProvider {
+    pub fn new(config: CustomConfig, record_name: String) -> Self {
+        CustomProvider {
+            config,
+            record_name,
+        }
+    }
+
+    fn context<'a>(
+        &'a self,
+        action: &'static str,
+        ip: Option<&'a str>,
+        record: Option<&DnsRecord>,
+    ) -> CustomContext<'a> {
+        CustomContext {
+            action,
+            domain: &self.record_name,
+            ip,
+            record_type: ip.map(get_record_type),
+            record: record.map(|r| CustomRecord {
+                id: r.id.clone(),
+                value: r.value.clone(),
+                record_type: Some(r.record_type.clone()),
+            }),
+            vars: &self.config.vars,
+        }
+    }
+
+    /// 执行一次操作，HTTP模式返回响应JSON，外部程序模式返回程序的响应
+    fn execute(
+        &self,
+        action: &'static str,
+        ip: Option<&str>,
+        record: Option<&DnsRecord>,
+    ) -> Result<Outcome, Error> {
+        let context = self.context(action, ip, record);
+
+        if let Some(command) = &self.config.command {
+            let timeout = self.config.timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT);
+            return run_command(command, &self.config.args, timeout, &context)
+                .map(Outcome::Command);
+        }
+
+        let request = match action {
+            "get" => self.config.get.as_ref(),
+            "modify" => self.config.modify.as_ref().or(self.config.add.as_ref()),
+            _ => self.config.add.as_ref(),
+        }
+        .ok_or_else(|| anyhow!("custom provider: {action} is not configured"))?;
+        send_request(request, &context).map(Outcome::Http)
+    }
+}
+
+/// 一次操作的结果
+enum Outcome {
+    /// HTTP模式：解析后的响应JSON（响应不是JSON时为Null，get返回404时为None）
+    Http(Option<Value>),
+    Command(CustomCommandResponse),
+}
+
+impl DnsProvider for CustomProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let record = match self.execute("get", None, None)? {
+            Outcome::Command(response) => response.record,
+            Outcome::Http(None) => None,
+            Outcome::Http(Some(json)) => {
+                let get = self
+                    .config
+                    .get
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("custom provider: get is not configured"))?;
+                let extract = |path: &Option<String>| -> Result<Option<String>, Error> {
+                    match path {
+                        Some(path) => json_path_first(path, &json),
+                        None => Ok(None),
+                    }
+                };
+                match extract(&get.record_value)? {
+                    Some(value) => Some(CustomRecord {
+                        id: extract(&get.record_id)?.unwrap_or_default(),
+                        value,
+                        record_type: extract(&get.record_type)?,
+                    }),
+                    None => None,
+                }
+            }
+        };
+
+        Ok(record.map(|record| {
+            info!("current custom record is {:?}", record);
+            DnsRecord {
+                id: record.id,
+                name: self.record_name.clone(),
+                record_type: record
+                    .record_type
+                    .unwrap_or_else(|| get_record_type(&record.value).to_string()),
+                value: record.value,
+            }
+        }))
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        self.execute("modify", Some(current_ip), Some(record))?;
+        debug!("custom modify result: success");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        self.execute("add", Some(current_ip), None)?;
+        debug!("custom add result: success");
+        Ok(())
+    }
+}
+
+// ========== HTTP 模板模式 ==========
+
+fn template_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    // 引用不存在的变量时报错，避免拼出错误的URL
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env
+}
+
+fn render(env: &Environment, template: &str, context: &CustomContext) -> Result<String, Error> {
+    env.render_str(template, context)
+        .map_err(|e| anyhow!("Failed to render template \"{}\": {}", template, e))
+}
+
+/// 发送请求并检查成功条件，get请求返回404时视为记录不存在（返回None）
+fn send_request(request: &CustomRequest, context: &CustomContext) -> Result<Option<Value>, Error> {
+    let env = template_env();
+    let method = Method::from_bytes(request.method.to_ascii_uppercase().as_bytes())?;
+    let url = render(&env, &request.url, context)?;
+
+    let client = crate::http_client()?;
+    let mut builder = client.request(method.clone(), &url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, render(&env, value, context)?);
+    }
+    if let Some(body) = &request.body {
+        builder = builder.body(render(&env, body, context)?);
+    }
+    let response = builder.send()?;
+    let status = response.status();
+    let text = response.text()?;
+    debug!(
+        "custom {} {} {} result: {}",
+        context.action, method, url, text
+    );
+
+    if context.action == "get"
+        && status == StatusCode::NOT_FOUND
+        && !request.success_status.contains(&status.as_u16())
+    {
+        return Ok(None);
+    }
+    let status_ok = if request.success_status.is_empty() {
+        status.is_success()
+    } else {
+        request.success_status.contains(&status.as_u16())
+    };
+    if !status_ok {
+        return Err(anyhow!(
+            "custom provider {} failed ({}): {}",
+            context.action,
+            status,
+            text
+        ));
+    }
+
+    let json = serde_json::from_str::<Value>(&text).ok();
+    if let Some(path) = &request.success_path {
+        let Some(json) = &json else {
+            warn!("error parse custom result: {text}");
+            return Err(anyhow!(
+                "custom provider {}: response is not JSON, cannot check success_path",
+                context.action
+            ));
+        };
+        let matched = json_path_first(path, json)?;
+        let ok = match (&matched, &request.success_value) {
+            (None, _) => false,
+            (Some(value), Some(expected)) => value == expected,
+            (Some(_), None) => true,
+        };
+        if !ok {
+            return Err(anyhow!(
+                "custom provider {}: success condition {} not met: {}",
+                context.action,
+                path,
+                text
+            ));
+        }
+    }
+    Ok(Some(json.unwrap_or(Value::Null)))
+}
+
+/// 返回JSONPath匹配到的第一个值，字符串原样返回，其他类型转为JSON文本
+fn json_path_first(path: &str, json: &Value) -> Result<Option<String>, Error> {
+    let path = JsonPath::parse(path).map_err(|e| anyhow!("Invalid JSONPath {}: {}", path, e))?;
+    Ok(path.query(json).first().map(|value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }))
+}
+
+// ========== 外部程序模式 ==========
+
+/// 将请求JSON写入程序的stdin，从stdout读取响应JSON，程序退出码非0、返回error或超时时视为失败
+fn run_command(
+    command: &str,
+    args: &[String],
+    timeout: Duration,
+    context: &CustomContext,
+) -> Result<CustomCommandResponse, Error> {
+    let input = serde_json::to_vec(context)?;
+    let output =
+        crate::process::output_with_timeout(Command::new(command).args(args), Some(input), timeout)
+            .map_err(|e| anyhow!("Failed to execute {}: {}", command, e))?
+            .ok_or_else(|| {
+                anyhow!(
+                    "custom command {} timed out after {}s",
+                    context.action,
+                    timeout.as_secs()
+                )
+            })?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        info!("custom command stderr: {}", stderr.trim());
+    }
+    if !output.status.success() {
+        return Err(anyhow!(
+            "custom command {} failed with exit code {:?}: {}",
+            context.action,
+            output.status.code(),
+            stderr.trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let response: CustomCommandResponse = if stdout.trim().is_empty() {
+        CustomCommandResponse::default()
+    } else {
+        serde_json::from_str(&stdout).map_err(|e| {
+            warn!("error parse custom command result: {stdout}");
+            anyhow!(e)
+        })?
+    };
+    if let Some(error) = response.error {
+        return Err(anyhow!(
+            "custom command {} failed: {}",
+            context.action,
+            error
+        ));
+    }
+    Ok(response)
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的custom provider配置，对应 `custom` 表
+#[derive(Deserialize, Debug)]
+pub struct CustomProviderConfig {
+    pub custom: CustomConfig,
+}
+
+impl ProviderConfig for CustomProviderConfig {
+    const NAME: &'static str = "custom";
+
+    fn validate(&self, _domain: &str) -> Result<(), Error> {
+        self.custom.validate()
+    }
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(CustomProvider::new(
+            self.custom,
+            context.domain.to_string(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_validate_and_render() {
+        let config: CustomConfig = toml::from_str(
+            r#"
+vars = { token = "secret" }
+
+[get]
+url = "https://api.example.com/records?name={{ domain }}"
+headers = { Authorization = "Bearer {{ vars.token }}" }
+record_id = "$.records[0].id"
+record_value = "$.records[0].content"
+
+[add]
+method = "post"
+url = "https://api.example.com/records"
+body = '{"name":"{{ domain }}","type":"{{ record_type }}","content":"{{ ip }}"}'
+success_path = "$.success"
+success_value = "true"
+"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        let context = CustomContext {
+            action: "add",
+            domain: "home.example.com",
+            ip: Some("2001:db8::1"),
+            record_type: Some("AAAA"),
+            record: None,
+            vars: &config.vars,
+        };
+        let env = template_env();
+        let add = config.add.as_ref().unwrap();
+        assert_eq!(
+            render(&env, add.body.as_ref().unwrap(), &context).unwrap(),
+            r#"{"name":"home.example.com","type":"AAAA","content":"2001:db8::1"}"#
+        );
+        // add 时没有当前记录，引用 record 应当报错
+        assert!(render(&env, "{{ record.id }}", &context).is_err());
+
+        let json: Value =
+            serde_json::from_str(r#"{"records":[{"id":7,"content":"1.2.3.4"}]}"#).unwrap();
+        assert_eq!(
+            json_path_first("$.records[0].id", &json).unwrap(),
+            Some("7".to_string())
+        );
+        assert_eq!(json_path_first("$.records[1].id", &json).unwrap(), None);
+
+        let invalid: CustomConfig = toml::from_str(
+            r#"
+[get]
+url = "https://api.example.com/{{ domain"
+record_value = "$.content"
+[add]
+url = "https://api.example.com/"
+"#,
+        )
+        .unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_command_timeout() {
+        let config: CustomConfig = toml::from_str(r#"command = "cat""#).unwrap();
+        config.validate().unwrap();
+        let provider = CustomProvider::new(config, "home.example.com".to_string());
+        // cat原样输出请求，其中没有record，视为记录不存在
+        assert!(provider.get_record().unwrap().is_none());
+
+        let config: CustomConfig = toml::from_str(
+            r#"command = "sleep"
+args = ["10"]
+timeout = "1s""#,
+        )
+        .unwrap();
+        assert_eq!(config.timeout, Some(Duration::from_secs(1)));
+        let provider = CustomProvider::new(config, "home.example.com".to_string());
+        let started = Instant::now();
+        let err = provider.get_record().unwrap_err();
+        assert!(err.to_string().contains("timed out after 1s"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let zero: CustomConfig = toml::from_str(
+            r#"command = "cat"
+timeout = "0s""#,
+        )
+        .unwrap();
+        assert!(zero.validate().is_err());
+    }
+}
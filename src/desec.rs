@@ -0,0 +1,298 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const DESEC_API: &str = "https://desec.io/api/v1";
+/// deSEC 要求的最小TTL
+const DESEC_MIN_TTL: u32 = 3600;
+
+// ========== deSEC 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DesecDomain {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DesecRrset {
+    subname: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    records: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DesecRrsetRequest<'a> {
+    subname: &'a str,
+    #[serde(rename = "type")]
+    record_type: &'a str,
+    ttl: u32,
+    records: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DesecRecordsPatch {
+    records: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DesecErrorResponse {
+    detail: String,
+}
+
+// ========== deSEC Provider 实现 ==========
+
+pub struct DesecProvider {
+    api: String,
+    token: String,
+    record_name: String,
+    record_type: &'static str,
+    /// 已查询到的(托管域名, subname)，同一次更新中只查询一次
+    zone: Mutex<Option<(String, String)>>,
+}
+
+impl DesecProvider {
+    pub fn new(token: String, record_name: String) -> Self {
+        DesecProvider {
+            api: DESEC_API.to_string(),
+            token,
+            record_name,
+            record_type: "A",
+            zone: Mutex::new(None),
+        }
+    }
+
+    /// PATCH的rrset类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 带上Token发送请求，被限流 (429) 时返回错误，由调度按退避间隔重试，不在这里阻塞等待
+    fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let response = request
+            .header("Authorization", format!("Token {}", self.token))
+            .send()?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("unknown")
+                .trim()
+                .to_string();
+            return Err(anyhow!("deSEC API throttled, retry after {}s", retry_after));
+        }
+        Ok(response)
+    }
+
+    /// 检查HTTP状态码，非2xx时解析deSEC的错误信息
+    fn check_response(response: Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<DesecErrorResponse>(&text)
+                .map(|e| e.detail)
+                .unwrap_or(text);
+            Err(anyhow!("deSEC API error ({}): {}", status, message))
+        }
+    }
+
+    /// 获取(托管域名, subname)，根域名的subname为空字符串，结果缓存在provider上
+    fn get_zone(&self) -> Result<(String, String), Error> {
+        let mut zone = self.zone.lock().unwrap();
+        if let Some(zone) = zone.as_ref() {
+            return Ok(zone.clone());
+        }
+        let client = crate::http_client()?;
+        let response = self.send(
+            client
+                .get(format!("{}/domains/", self.api))
+                .query(&[("owns_qname", self.record_name.as_str())]),
+        )?;
+        let text = Self::check_response(response)?;
+        let domains: Vec<DesecDomain> = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse domain list response: {}", e))?;
+
+        let domain = domains
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))?;
+        let name = relative_name(&self.record_name, &domain.name)
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))?;
+        let subname = if name == "@" { String::new() } else { name };
+        *zone = Some((domain.name.clone(), subname.clone()));
+        Ok((domain.name, subname))
+    }
+
+    /// rrset的URL，根域名在URL中使用 "@" 表示
+    fn rrset_url(&self, domain: &str, subname: &str, record_type: &str) -> String {
+        let subname = if subname.is_empty() { "@" } else { subname };
+        format!(
+            "{}/domains/{domain}/rrsets/{subname}/{record_type}/",
+            self.api
+        )
+    }
+}
+
+impl DnsProvider for DesecProvider {
+    /// 获取DNS记录，只查询record_type类型的rrset
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (domain, subname) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let url = self.rrset_url(&domain, &subname, self.record_type);
+        let res = self.send(client.get(&url))?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let text = Self::check_response(res)?;
+        let rrset: DesecRrset = match serde_json::from_str(&text) {
+            Ok(rrset) => rrset,
+            Err(err) => {
+                warn!("error parse desec result: {text}");
+                return Err(anyhow!(err));
+            }
+        };
+        info!("current desec rrset is {:?}", rrset);
+        Ok(rrset.records.first().map(|value| DnsRecord {
+            id: rrset.subname.clone(),
+            name: rrset.subname.clone(),
+            value: value.clone(),
+            record_type: rrset.record_type.clone(),
+        }))
+    }
+
+    /// 修改DNS记录，只PATCH同一类型的rrset
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let (domain, subname) = self.get_zone()?;
+        let client = crate::http_client()?;
+        let url = self.rrset_url(&domain, &subname, &record.record_type);
+        let res = self.send(client.patch(&url).json(&DesecRecordsPatch {
+            records: vec![current_ip.to_string()],
+        }))?;
+        let text = Self::check_response(res)?;
+        debug!("desec modify result: {text}");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let (domain, subname) = self.get_zone()?;
+        let client = crate::http_client()?;
+        let url = format!("{}/domains/{domain}/rrsets/", self.api);
+        let res = self.send(client.post(&url).json(&DesecRrsetRequest {
+            subname: &subname,
+            record_type: get_record_type(current_ip),
+            ttl: DESEC_MIN_TTL,
+            records: vec![current_ip.to_string()],
+        }))?;
+        let text = Self::check_response(res)?;
+        debug!("desec add result: {text}");
+        Ok(())
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的deSEC配置
+#[derive(Deserialize, Debug)]
+pub struct DesecConfig {
+    /// deSEC API Token
+    pub desec_token: String,
+}
+
+impl ProviderConfig for DesecConfig {
+    const NAME: &'static str = "desec";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            DesecProvider::new(self.desec_token, context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        const DOMAINS: &str = r#"[{"name": "example.com"}]"#;
+        let (address, handle) = serve_http(vec![
+            DOMAINS,
+            r#"{"subname": "home", "type": "AAAA", "records": ["2001:db8::1"]}"#,
+            "{}",
+        ]);
+        let mut provider = DesecProvider::new("token".to_string(), "home.example.com".to_string())
+            .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // zone只查询一次；只PATCH AAAA rrset，不清空A rrset
+        let result = provider.update_dns_record("2001:db8::2").unwrap();
+        assert!(matches!(
+            result,
+            DnsUpdateResult::Changed { old_ip, .. } if old_ip == "2001:db8::1"
+        ));
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(
+            requests[0]
+                .0
+                .starts_with("GET /domains/?owns_qname=home.example.com ")
+        );
+        assert!(
+            requests[1]
+                .0
+                .starts_with("GET /domains/example.com/rrsets/home/AAAA/ ")
+        );
+        assert!(
+            requests[2]
+                .0
+                .starts_with("PATCH /domains/example.com/rrsets/home/AAAA/ ")
+        );
+        assert_eq!(requests[2].1, r#"{"records":["2001:db8::2"]}"#);
+    }
+
+    #[test]
+    fn test_throttled() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 60\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+        });
+        let mut provider = DesecProvider::new("token".to_string(), "home.example.com".to_string());
+        provider.api = format!("http://{address}");
+
+        // 被限流时不等待重试，直接返回错误，交给调度退避
+        let error = provider.get_record().unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(error.to_string(), "deSEC API throttled, retry after 60s");
+        assert_eq!(
+            crate::retry::classify(&error),
+            crate::retry::ErrorKind::Other
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const DIGITALOCEAN_API: &str = "https://api.digitalocean.com/v2";
+
+/// 查询域名列表时每页的数量 (API允许的最大值)
+const DOMAIN_PAGE_SIZE: usize = 200;
+
+// 全局的 DigitalOcean 域名缓存: api_token -> 已托管的域名列表
+static DIGITALOCEAN_DOMAIN_CACHE: LazyLock<Mutex<HashMap<String, Vec<String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// ========== DigitalOcean 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DigitaloceanDomainListResponse {
+    domains: Vec<DigitaloceanDomain>,
+    #[serde(default)]
+    links: DigitaloceanLinks,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DigitaloceanLinks {
+    #[serde(default)]
+    pages: DigitaloceanPages,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DigitaloceanPages {
+    /// 下一页的地址，最后一页时不存在
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DigitaloceanDomain {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DigitaloceanRecordListResponse {
+    domain_records: Vec<DigitaloceanRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct DigitaloceanRecord {
+    id: u64,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    data: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DigitaloceanRecordRequest {
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    data: String,
+    ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct DigitaloceanErrorResponse {
+    id: String,
+    message: String,
+}
+
+// ========== DigitalOcean Provider 实现 ==========
+
+pub struct DigitaloceanProvider {
+    api: String,
+    api_token: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl DigitaloceanProvider {
+    pub fn new(api_token: String, record_name: String) -> Self {
+        DigitaloceanProvider {
+            api: DIGITALOCEAN_API.to_string(),
+            api_token,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 按该类型过滤域名记录，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 分页获取账户下的全部域名
+    fn list_domains(&self) -> Result<Vec<String>, Error> {
+        let client = crate::http_client()?;
+        let mut domains = Vec::new();
+        for page in 1.. {
+            let response = client
+                .get(format!(
+                    "{}/domains?page={page}&per_page={DOMAIN_PAGE_SIZE}",
+                    self.api
+                ))
+                .bearer_auth(&self.api_token)
+                .send()
+                .map_err(|e| anyhow!("Failed to query domain list: {}", e))?;
+            let text = Self::check_response(response)?;
+            let domain_list: DigitaloceanDomainListResponse = serde_json::from_str(&text)
+                .map_err(|e| anyhow!("Failed to parse domain list response: {}", e))?;
+            domains.extend(domain_list.domains.into_iter().map(|d| d.name));
+            if domain_list.links.pages.next.is_none() {
+                break;
+            }
+        }
+        Ok(domains)
+    }
+
+    /// 获取(托管域名, 相对记录名)，域名列表优先从缓存读取
+    fn get_zone(&self) -> Result<(String, String), Error> {
+        let cached = DIGITALOCEAN_DOMAIN_CACHE
+            .lock()
+            .unwrap()
+            .get(&self.api_token)
+            .cloned();
+        let domains = match cached {
+            Some(domains) => domains,
+            None => {
+                // 缓存未命中，调用API查询
+                let domains = self.list_domains()?;
+                DIGITALOCEAN_DOMAIN_CACHE
+                    .lock()
+                    .unwrap()
+                    .insert(self.api_token.clone(), domains.clone());
+                domains
+            }
+        };
+
+        domains
+            .into_iter()
+            .filter_map(|zone| relative_name(&self.record_name, &zone).map(|name| (zone, name)))
+            .max_by_key(|(zone, _)| zone.len())
+            .inspect(|(zone, _)| {
+                debug!(
+                    "Found digitalocean domain for {}: {}",
+                    self.record_name, zone
+                )
+            })
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))
+    }
+
+    /// 检查HTTP状态码，非2xx时解析DigitalOcean的错误信息
+    fn check_response(response: reqwest::blocking::Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<DigitaloceanErrorResponse>(&text)
+                .map(|e| format!("{}: {}", e.id, e.message))
+                .unwrap_or(text);
+            Err(anyhow!("DigitalOcean API error ({}): {}", status, message))
+        }
+    }
+}
+
+impl DnsProvider for DigitaloceanProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (zone, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        // name 过滤参数需要使用完整域名
+        let res = client
+            .get(format!("{}/domains/{zone}/records", self.api))
+            .query(&[
+                ("name", self.record_name.as_str()),
+                ("type", self.record_type),
+            ])
+            .bearer_auth(&self.api_token)
+            .send()?;
+        let text = Self::check_response(res)?;
+        let result: serde_json::Result<DigitaloceanRecordListResponse> =
+            serde_json::from_str(&text);
+
+        match result {
+            Ok(response) => {
+                let record = response
+                    .domain_records
+                    .into_iter()
+                    .find(|record| record.name == name && record.record_type == self.record_type);
+                match record {
+                    Some(record) => {
+                        info!("current digitalocean record is {:?}", record);
+                        Ok(Some(DnsRecord {
+                            id: record.id.to_string(),
+                            name: record.name,
+                            value: record.data,
+                            record_type: record.record_type,
+                        }))
+                    }
+                    None => Ok(None),
+                }
+            }
+            Err(err) => {
+                warn!("error parse digitalocean result: {text}");
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let (zone, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let update_request = DigitaloceanRecordRequest {
+            record_type: record.record_type.clone(),
+            name,
+            data: current_ip.to_string(),
+            ttl: 60,
+        };
+
+        let res = client
+            .put(format!("{}/domains/{zone}/records/{}", self.api, record.id))
+            .bearer_auth(&self.api_token)
+            .json(&update_request)
+            .send()?;
+        let text = Self::check_response(res)?;
+        debug!("digitalocean modify result: {text}");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let (zone, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let create_request = DigitaloceanRecordRequest {
+            record_type: get_record_type(current_ip).to_string(),
+            name,
+            data: current_ip.to_string(),
+            ttl: 60,
+        };
+
+        let res = client
+            .post(format!("{}/domains/{zone}/records", self.api))
+            .bearer_auth(&self.api_token)
+            .json(&create_request)
+            .send()?;
+        let text = Self::check_response(res)?;
+        debug!("digitalocean add result: {text}");
+        Ok(())
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的DigitalOcean配置
+#[derive(Deserialize, Debug)]
+pub struct DigitaloceanConfig {
+    /// DigitalOcean API Token
+    pub digitalocean_token: String,
+}
+
+impl ProviderConfig for DigitaloceanConfig {
+    const NAME: &'static str = "digitalocean";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            DigitaloceanProvider::new(self.digitalocean_token, context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        let (address, handle) = serve_http(vec![
+            r#"{"domains": [{"name": "example.com"}]}"#,
+            r#"{"domain_records": [
+                {"id": 1, "type": "A", "name": "home", "data": "1.2.3.4"}
+            ]}"#,
+            "{}",
+        ]);
+        let mut provider =
+            DigitaloceanProvider::new("test-token".to_string(), "home.example.com".to_string())
+                .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // 只有A记录时新建AAAA记录，不修改A记录
+        let result = provider.update_dns_record("2001:db8::1").unwrap();
+        assert!(matches!(result, DnsUpdateResult::Created));
+        let requests = handle.join().unwrap();
+        assert!(
+            requests[1]
+                .0
+                .starts_with("GET /domains/example.com/records?name=home.example.com&type=AAAA ")
+        );
+        assert!(
+            requests[2]
+                .0
+                .starts_with("POST /domains/example.com/records ")
+        );
+        assert!(requests[2].1.contains(r#""type":"AAAA""#));
+    }
+
+    #[test]
+    fn test_domain_pages() {
+        let (address, handle) = serve_http(vec![
+            r#"{"domains": [{"name": "other.com"}],
+                "links": {"pages": {"next": "https://api.digitalocean.com/v2/domains?page=2"}}}"#,
+            r#"{"domains": [{"name": "example.com"}], "links": {}}"#,
+        ]);
+        let mut provider =
+            DigitaloceanProvider::new("paged-token".to_string(), "home.example.com".to_string());
+        provider.api = format!("http://{address}");
+
+        assert_eq!(
+            provider.get_zone().unwrap(),
+            ("example.com".to_string(), "home".to_string())
+        );
+        let requests = handle.join().unwrap();
+        assert!(
+            requests[0]
+                .0
+                .starts_with("GET /domains?page=1&per_page=200 ")
+        );
+        assert!(
+            requests[1]
+                .0
+                .starts_with("GET /domains?page=2&per_page=200 ")
+        );
+    }
+}
@@ -2,9 +2,9 @@ use anyhow::{Error, anyhow};
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::IpAddr;
 
-use crate::{DnsProvider, DnsRecord};
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type};
 
 // ========== DNSPod 相关结构 ==========
 
@@ -39,15 +39,6 @@ impl DnspodProvider {
             sub_domain,
         }
     }
-
-    /// 判断IP地址类型，返回对应的记录类型
-    fn get_record_type(ip: &str) -> &'static str {
-        match ip.parse::<IpAddr>() {
-            Ok(IpAddr::V4(_)) => "A",
-            Ok(IpAddr::V6(_)) => "AAAA",
-            Err(_) => "A", // 默认使用A记录
-        }
-    }
 }
 
 impl DnsProvider for DnspodProvider {
@@ -61,7 +52,7 @@ impl DnsProvider for DnspodProvider {
         params.insert("domain", &self.domain);
         params.insert("sub_domain", &self.sub_domain);
 
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let res = client
             .post("https://dnsapi.cn/Record.List")
             .form(&params)
@@ -92,7 +83,7 @@ impl DnsProvider for DnspodProvider {
 
     /// 修改DNS记录
     fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let mut params: HashMap<&'static str, &str> = HashMap::new();
 
         // 从DNSPod获取记录时，我们需要line_id，这里我们从原始记录获取
@@ -107,7 +98,7 @@ impl DnsProvider for DnspodProvider {
         params.insert("sub_domain", &record.name);
         params.insert("record_id", record_id);
         params.insert("record_line_id", "0"); // 默认线路
-        params.insert("record_type", Self::get_record_type(current_ip));
+        params.insert("record_type", get_record_type(current_ip));
         params.insert("value", current_ip);
 
         let res = client
@@ -129,7 +120,7 @@ impl DnsProvider for DnspodProvider {
 
     /// 添加DNS记录
     fn add_record(&self, current_ip: &str) -> Result<(), Error> {
-        let client = reqwest::blocking::Client::new();
+        let client = crate::http_client()?;
         let mut params: HashMap<&'static str, &str> = HashMap::new();
         params.insert("login_token", &self.token);
         params.insert("format", "json");
@@ -137,7 +128,7 @@ impl DnsProvider for DnspodProvider {
         params.insert("lang", "en");
         params.insert("domain", &self.domain);
         params.insert("sub_domain", &self.sub_domain);
-        params.insert("record_type", Self::get_record_type(current_ip));
+        params.insert("record_type", get_record_type(current_ip));
         params.insert("record_line", "默认");
         params.insert("value", current_ip);
 
@@ -158,3 +149,116 @@ impl DnsProvider for DnspodProvider {
         Err(anyhow!("Error adding record"))
     }
 }
+
+/// 解析完整域名，返回(子域名, 主域名)
+/// 支持多级子域名：
+/// 例如: "sub.example.com" -> ("sub", "example.com")
+///      "api.v2.example.com" -> ("api.v2", "example.com")
+///      "deep.nested.sub.example.com" -> ("deep.nested.sub", "example.com")
+///      "@.example.com" -> ("@", "example.com")
+///      "example.com" -> ("@", "example.com")
+pub fn parse_domain(full_domain: &str) -> Result<(String, String), Error> {
+    let parts: Vec<&str> = full_domain.split('.').collect();
+
+    if parts.len() < 2 {
+        return Err(anyhow!("Invalid domain format: {}", full_domain));
+    }
+
+    if let Some(main_domain) = full_domain.strip_prefix("@.") {
+        // @.example.com -> ("@", "example.com")
+        Ok(("@".to_string(), main_domain.to_string()))
+    } else if parts.len() == 2 {
+        // example.com -> ("@", "example.com")
+        Ok(("@".to_string(), full_domain.to_string()))
+    } else {
+        // 对于多级域名，假设最后两个部分是主域名，其余为子域名
+        // sub.example.com -> ("sub", "example.com")
+        // api.v2.example.com -> ("api.v2", "example.com")
+        // deep.nested.sub.example.com -> ("deep.nested.sub", "example.com")
+        let main_domain_parts = &parts[parts.len() - 2..];
+        let subdomain_parts = &parts[..parts.len() - 2];
+
+        let main_domain = main_domain_parts.join(".");
+        let subdomain = subdomain_parts.join(".");
+
+        Ok((subdomain, main_domain))
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的DNSPod配置
+#[derive(Deserialize, Debug)]
+pub struct DnspodConfig {
+    /// DNSPod Token，格式为 "token_id,token_secret"
+    pub dnspod_token: String,
+}
+
+impl ProviderConfig for DnspodConfig {
+    const NAME: &'static str = "dnspod";
+
+    fn validate(&self, domain: &str) -> Result<(), Error> {
+        parse_domain(domain).map(|_| ())
+    }
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        let (subdomain, main_domain) = parse_domain(context.domain)?;
+        Ok(Box::new(DnspodProvider::new(
+            self.dnspod_token,
+            main_domain,
+            subdomain,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_domain() {
+        // 测试根域名
+        assert_eq!(
+            parse_domain("example.com").unwrap(),
+            ("@".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_domain("@.example.com").unwrap(),
+            ("@".to_string(), "example.com".to_string())
+        );
+
+        // 测试单级子域名
+        assert_eq!(
+            parse_domain("www.example.com").unwrap(),
+            ("www".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_domain("blog.example.com").unwrap(),
+            ("blog".to_string(), "example.com".to_string())
+        );
+
+        // 测试多级子域名
+        assert_eq!(
+            parse_domain("api.v2.example.com").unwrap(),
+            ("api.v2".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_domain("deep.nested.sub.example.com").unwrap(),
+            ("deep.nested.sub".to_string(), "example.com".to_string())
+        );
+        assert_eq!(
+            parse_domain("a.b.c.d.example.com").unwrap(),
+            ("a.b.c.d".to_string(), "example.com".to_string())
+        );
+
+        // 测试错误情况
+        assert!(parse_domain("invalid").is_err());
+        assert!(parse_domain("").is_err());
+
+        // 测试特殊情况
+        assert_eq!(
+            parse_domain("test.co.uk").unwrap(),
+            ("test".to_string(), "co.uk".to_string())
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info};
+use serde::Deserialize;
+use std::net::ToSocketAddrs;
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, DnsUpdateResult, get_record_type};
+
+const DUCKDNS_UPDATE_URL: &str = "https://www.duckdns.org/update";
+
+// ========== DuckDNS Provider 实现 ==========
+
+/// DuckDNS 只提供一个更新接口，没有查询记录的接口，
+/// 因此每次都调用更新接口，按响应判断记录是否变化，DNS解析的结果只用于记录变化前的IP
+pub struct DuckdnsProvider {
+    update_url: String,
+    token: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl DuckdnsProvider {
+    /// record_name 为 "myhost.duckdns.org" 或 "myhost"
+    pub fn new(token: String, record_name: String) -> Self {
+        DuckdnsProvider {
+            update_url: DUCKDNS_UPDATE_URL.to_string(),
+            token,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 查询和更新的记录类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 提取DuckDNS子域名
+    /// 例如: "myhost.duckdns.org" -> "myhost"
+    ///      "www.myhost.duckdns.org" -> "myhost"
+    fn subdomain(&self) -> &str {
+        let name = self.record_name.trim_end_matches('.');
+        let name = name.strip_suffix(".duckdns.org").unwrap_or(name);
+        name.rsplit('.').next().unwrap_or(name)
+    }
+
+    /// 调用更新接口，返回记录是否发生了变化
+    fn update(&self, current_ip: &str) -> Result<bool, Error> {
+        let ip_param = match get_record_type(current_ip) {
+            "AAAA" => "ipv6",
+            _ => "ip",
+        };
+        let client = crate::http_client()?;
+        let text = client
+            .get(&self.update_url)
+            .query(&[
+                ("domains", self.subdomain()),
+                ("token", self.token.as_str()),
+                (ip_param, current_ip),
+                ("verbose", "true"),
+            ])
+            .send()?
+            .text()?;
+
+        // verbose 响应格式: "OK\n<ipv4>\n<ipv6>\nUPDATED|NOCHANGE"，失败时为 "KO"
+        let mut lines = text.lines();
+        match lines.next() {
+            Some("OK") => {
+                let status = lines.last().unwrap_or_default().trim();
+                debug!("duckdns update result: {status}");
+                Ok(status != "NOCHANGE")
+            }
+            _ => Err(anyhow!(
+                "DuckDNS update failed for {}: {}",
+                self.subdomain(),
+                text.trim()
+            )),
+        }
+    }
+}
+
+impl DnsProvider for DuckdnsProvider {
+    /// 通过DNS解析获取当前记录，只取record_type对应地址族的地址，解析结果可能已被缓存
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let host = format!("{}.duckdns.org", self.subdomain());
+        let addr = match (host.as_str(), 0).to_socket_addrs() {
+            Ok(mut addrs) => addrs.find(|addr| addr.is_ipv6() == (self.record_type == "AAAA")),
+            Err(e) => {
+                debug!("resolve {host} failed: {e}");
+                None
+            }
+        };
+        Ok(addr.map(|addr| {
+            info!("current duckdns record of {host} is {}", addr.ip());
+            DnsRecord {
+                id: self.subdomain().to_string(),
+                name: host.clone(),
+                value: addr.ip().to_string(),
+                record_type: self.record_type.to_string(),
+            }
+        }))
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, _record: &DnsRecord) -> Result<(), Error> {
+        self.update(current_ip).map(|_| ())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        self.update(current_ip).map(|_| ())
+    }
+
+    /// 总是调用更新接口，按响应中的UPDATED/NOCHANGE判断记录是否变化
+    fn update_dns_record(&self, current_ip: &str) -> Result<DnsUpdateResult, Error> {
+        let record = self.get_record()?;
+        let record_id = Some(self.subdomain().to_string());
+        if !self.update(current_ip)? {
+            info!("ip not changed");
+            return Ok(DnsUpdateResult::Unchanged { record_id });
+        }
+        match record {
+            Some(record) => {
+                info!("ip changed from {} to {}", record.value, current_ip);
+                Ok(DnsUpdateResult::Changed {
+                    old_ip: record.value,
+                    record_id,
+                })
+            }
+            None => Ok(DnsUpdateResult::Created),
+        }
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的DuckDNS配置
+#[derive(Deserialize, Debug)]
+pub struct DuckdnsConfig {
+    /// DuckDNS Token
+    pub duckdns_token: String,
+}
+
+impl ProviderConfig for DuckdnsConfig {
+    const NAME: &'static str = "duckdns";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            DuckdnsProvider::new(self.duckdns_token, context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update() {
+        let (address, handle) = serve_http(vec![
+            "OK\n\n2001:db8::1\nUPDATED",
+            "OK\n\n2001:db8::1\nNOCHANGE",
+            "KO",
+        ]);
+        let mut provider =
+            DuckdnsProvider::new("token".to_string(), "myhost.duckdns.org".to_string())
+                .record_type("AAAA");
+        provider.update_url = format!("http://{address}/update");
+
+        assert!(provider.update("2001:db8::1").unwrap());
+        assert!(!provider.update("2001:db8::1").unwrap());
+        assert!(provider.update("2001:db8::1").is_err());
+        let requests = handle.join().unwrap();
+        assert!(requests[0].0.starts_with(
+            "GET /update?domains=myhost&token=token&ipv6=2001%3Adb8%3A%3A1&verbose=true "
+        ));
+    }
+}
@@ -0,0 +1,687 @@
+use anyhow::{Error, anyhow};
+use chrono::Local;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::net::IpAddr;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+/// 在zone文件中新增记录时使用的TTL
+const ZONE_RECORD_TTL: u32 = 60;
+
+// ========== 文件格式相关结构 ==========
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    /// BIND 格式的 zone 文件，修改记录后递增 SOA serial
+    Zone,
+    /// /etc/hosts 格式的文件（dnsmasq 的 addn-hosts、CoreDNS 的 hosts 插件）
+    #[default]
+    Hosts,
+}
+
+/// token在行内的字节范围
+type Span = (usize, usize);
+
+/// zone文件中的一条资源记录（只记录所在行内的rdata）
+#[derive(Debug)]
+struct ZoneEntry {
+    line: usize,
+    owner: String,
+    record_type: String,
+    rdata: Vec<Span>,
+}
+
+#[derive(Debug)]
+struct ZoneFile {
+    entries: Vec<ZoneEntry>,
+    /// SOA serial 所在的(行号, 范围)
+    serial: Option<(usize, Span)>,
+    /// 文件末尾生效的origin，新增记录时使用
+    origin: Option<String>,
+}
+
+/// hosts文件中的一行
+#[derive(Debug)]
+struct HostsEntry {
+    line: usize,
+    ip: IpAddr,
+    ip_span: Span,
+    names: Vec<String>,
+}
+
+/// 待写回文件的一处修改
+struct Edit {
+    line: usize,
+    span: Span,
+    text: String,
+}
+
+// ========== File Provider 实现 ==========
+
+pub struct FileProvider {
+    path: PathBuf,
+    format: FileFormat,
+    record_name: String,
+    origin: Option<String>,
+    reload_command: Option<String>,
+    record_type: &'static str,
+}
+
+impl FileProvider {
+    /// record_name 为完整域名，如 "home.example.com"
+    pub fn new(path: PathBuf, format: FileFormat, record_name: String) -> Self {
+        FileProvider {
+            path,
+            format,
+            record_name,
+            origin: None,
+            reload_command: None,
+            record_type: "A",
+        }
+    }
+
+    /// zone文件没有 $ORIGIN 指令时使用的origin
+    pub fn origin(mut self, origin: Option<String>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// 文件写入后执行的reload指令
+    pub fn reload_command(mut self, reload_command: Option<String>) -> Self {
+        self.reload_command = reload_command;
+        self
+    }
+
+    /// 查找和新增的记录类型，"A" 或 "AAAA"，只修改该类型的记录
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    fn read(&self) -> Result<String, Error> {
+        fs::read_to_string(&self.path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", self.path.display(), e))
+    }
+
+    fn record_fqdn(&self) -> String {
+        normalize_name(&self.record_name)
+    }
+
+    /// 在zone文件中查找目标域名的record_type类型记录
+    fn find_zone_entry<'a>(&self, zone: &'a ZoneFile) -> Option<&'a ZoneEntry> {
+        let fqdn = self.record_fqdn();
+        zone.entries
+            .iter()
+            .find(|e| e.owner == fqdn && e.record_type == self.record_type && !e.rdata.is_empty())
+    }
+
+    /// 在hosts文件中查找目标域名所在的行，只匹配与record_type地址族相同的IP
+    fn find_hosts_entry<'a>(&self, entries: &'a [HostsEntry]) -> Option<&'a HostsEntry> {
+        let is_ipv6 = self.record_type == "AAAA";
+        entries.iter().find(|e| {
+            e.ip.is_ipv6() == is_ipv6
+                && e.names
+                    .iter()
+                    .any(|n| n.eq_ignore_ascii_case(self.record_name.trim_end_matches('.')))
+        })
+    }
+
+    /// 写入文件并执行reload指令
+    fn commit(&self, content: &str, current_ip: &str) -> Result<(), Error> {
+        write_atomic(&self.path, content)?;
+        info!("{} updated for {}", self.path.display(), self.record_name);
+        if let Some(reload_command) = &self.reload_command {
+            run_reload_command(reload_command, &self.record_name, current_ip)?;
+        }
+        Ok(())
+    }
+}
+
+impl DnsProvider for FileProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let content = self.read()?;
+        let record = match self.format {
+            FileFormat::Zone => {
+                let zone = parse_zone(&content, self.origin.as_deref())?;
+                self.find_zone_entry(&zone).map(|entry| {
+                    let line = line_at(&content, entry.line);
+                    let (start, end) = entry.rdata[0];
+                    DnsRecord {
+                        id: (entry.line + 1).to_string(),
+                        name: entry.owner.clone(),
+                        value: line[start..end].to_string(),
+                        record_type: entry.record_type.clone(),
+                    }
+                })
+            }
+            FileFormat::Hosts => {
+                let entries = parse_hosts(&content);
+                self.find_hosts_entry(&entries).map(|entry| DnsRecord {
+                    id: (entry.line + 1).to_string(),
+                    name: self.record_name.clone(),
+                    value: entry.ip.to_string(),
+                    record_type: self.record_type.to_string(),
+                })
+            }
+        };
+        if let Some(record) = &record {
+            info!("current file record is {:?}", record);
+        }
+        Ok(record)
+    }
+
+    /// 修改DNS记录：重新读取文件并定位记录，只替换IP
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let content = self.read()?;
+        let mut edits = Vec::new();
+
+        match self.format {
+            FileFormat::Zone => {
+                let zone = parse_zone(&content, self.origin.as_deref())?;
+                let entry = self.find_zone_entry(&zone).ok_or_else(|| {
+                    anyhow!(
+                        "Record {} disappeared from {} before update",
+                        record.name,
+                        self.path.display()
+                    )
+                })?;
+                edits.push(Edit {
+                    line: entry.line,
+                    span: entry.rdata[0],
+                    text: current_ip.to_string(),
+                });
+                edits.push(bump_serial(&content, &zone)?);
+            }
+            FileFormat::Hosts => {
+                let entries = parse_hosts(&content);
+                let entry = self.find_hosts_entry(&entries).ok_or_else(|| {
+                    anyhow!(
+                        "Record {} disappeared from {} before update",
+                        record.name,
+                        self.path.display()
+                    )
+                })?;
+                edits.push(Edit {
+                    line: entry.line,
+                    span: entry.ip_span,
+                    text: current_ip.to_string(),
+                });
+            }
+        }
+
+        let content = apply_edits(&content, edits);
+        debug!("file modify result: {}", self.path.display());
+        self.commit(&content, current_ip)
+    }
+
+    /// 添加DNS记录：在文件末尾追加一行
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let content = self.read()?;
+        let (content, line) = match self.format {
+            FileFormat::Zone => {
+                let zone = parse_zone(&content, self.origin.as_deref())?;
+                let fqdn = self.record_fqdn();
+                let owner = zone
+                    .origin
+                    .as_deref()
+                    .and_then(|origin| relative_name(&fqdn, origin))
+                    .unwrap_or_else(|| format!("{fqdn}."));
+                let serial = bump_serial(&content, &zone)?;
+                let line = format!(
+                    "{owner}\t{ZONE_RECORD_TTL}\tIN\t{}\t{current_ip}\n",
+                    self.record_type
+                );
+                (apply_edits(&content, vec![serial]), line)
+            }
+            FileFormat::Hosts => {
+                let line = format!("{current_ip}\t{}\n", self.record_name.trim_end_matches('.'));
+                (content, line)
+            }
+        };
+        self.commit(&append_line(&content, &line), current_ip)
+    }
+}
+
+// ========== 解析与写回 ==========
+
+/// 域名统一为小写且不带末尾的点
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// 按原样（含换行符）切分行，保证写回时不改变换行风格
+fn split_lines(content: &str) -> Vec<&str> {
+    content.split_inclusive('\n').collect()
+}
+
+fn line_at(content: &str, line: usize) -> &str {
+    split_lines(content).get(line).copied().unwrap_or_default()
+}
+
+/// 将一行拆分为token的字节范围，遇到注释符号停止；引号内的内容视为一个token，括号单独成为token
+fn tokenize(line: &str, comment: u8) -> Vec<Span> {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == comment {
+            break;
+        }
+        let start = i;
+        match c {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            b'(' | b')' => i += 1,
+            _ => {
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !matches!(bytes[i], b'(' | b')' | b'"')
+                    && bytes[i] != comment
+                {
+                    i += 1;
+                }
+            }
+        }
+        tokens.push((start, i));
+    }
+    tokens
+}
+
+/// 将zone文件中的名字转换为不带末尾点的完整域名
+fn absolute_name(name: &str, origin: Option<&str>) -> Result<String, Error> {
+    if let Some(name) = name.strip_suffix('.') {
+        return Ok(name.to_ascii_lowercase());
+    }
+    let origin = origin.ok_or_else(|| {
+        anyhow!(
+            "Zone file uses relative name \"{}\" but has no $ORIGIN, please configure the origin",
+            name
+        )
+    })?;
+    if name == "@" {
+        Ok(origin.to_string())
+    } else {
+        Ok(format!("{}.{}", name.to_ascii_lowercase(), origin))
+    }
+}
+
+fn parse_zone(content: &str, default_origin: Option<&str>) -> Result<ZoneFile, Error> {
+    let mut origin = default_origin.map(normalize_name);
+    let mut entries = Vec::new();
+    let mut serial = None;
+    let mut last_owner: Option<String> = None;
+    let mut depth = 0usize;
+    // 跨行的SOA rdata，收集到第3个token（serial）为止
+    let mut soa_rdata: Option<Vec<(usize, Span)>> = None;
+
+    for (line_no, line) in split_lines(content).into_iter().enumerate() {
+        let tokens = tokenize(line, b';');
+        if tokens.is_empty() {
+            continue;
+        }
+        let continuation = depth > 0;
+        let mut fields = Vec::new();
+        for &(start, end) in &tokens {
+            match &line[start..end] {
+                "(" => depth += 1,
+                ")" => depth = depth.saturating_sub(1),
+                _ => fields.push((start, end)),
+            }
+        }
+        let text = |(start, end): Span| &line[start..end];
+
+        if continuation {
+            if let Some(rdata) = soa_rdata.as_mut() {
+                rdata.extend(fields.iter().map(|&span| (line_no, span)));
+            }
+        } else if let Some(&first) = fields.first() {
+            if text(first).starts_with('$') {
+                if text(first).eq_ignore_ascii_case("$ORIGIN")
+                    && let Some(&name) = fields.get(1)
+                {
+                    origin = Some(absolute_name(text(name), origin.as_deref())?);
+                }
+                continue;
+            }
+
+            let mut idx = 0;
+            if !line.starts_with(|c: char| c.is_ascii_whitespace()) {
+                last_owner = Some(absolute_name(text(first), origin.as_deref())?);
+                idx = 1;
+            }
+            let owner = last_owner
+                .clone()
+                .ok_or_else(|| anyhow!("Zone file line {} has no owner name", line_no + 1))?;
+            // 跳过可选的TTL和class
+            while idx < fields.len() {
+                let field = text(fields[idx]);
+                let is_class = ["IN", "CH", "HS"]
+                    .iter()
+                    .any(|c| field.eq_ignore_ascii_case(c));
+                if !is_class && !field.starts_with(|c: char| c.is_ascii_digit()) {
+                    break;
+                }
+                idx += 1;
+            }
+            let Some(&type_span) = fields.get(idx) else {
+                continue;
+            };
+            let record_type = text(type_span).to_ascii_uppercase();
+            let rdata = fields[idx + 1..].to_vec();
+            if record_type == "SOA" && serial.is_none() {
+                soa_rdata = Some(rdata.iter().map(|&span| (line_no, span)).collect());
+            }
+            entries.push(ZoneEntry {
+                line: line_no,
+                owner,
+                record_type,
+                rdata,
+            });
+        }
+
+        // SOA rdata 依次为 mname rname serial ...
+        if serial.is_none()
+            && let Some(rdata) = &soa_rdata
+            && rdata.len() >= 3
+        {
+            serial = Some(rdata[2]);
+            soa_rdata = None;
+        }
+    }
+
+    Ok(ZoneFile {
+        entries,
+        serial,
+        origin,
+    })
+}
+
+/// 计算新的SOA serial：日期格式(YYYYMMDDnn)的serial跨天时重置为当天的第一个序号，否则加一
+fn next_serial(serial: u32, today: u32) -> u32 {
+    if serial >= 1_000_000_000 && serial < today {
+        today
+    } else {
+        serial.wrapping_add(1)
+    }
+}
+
+fn bump_serial(content: &str, zone: &ZoneFile) -> Result<Edit, Error> {
+    let (line, span) = zone
+        .serial
+        .ok_or_else(|| anyhow!("No SOA record found in zone file"))?;
+    let text = &line_at(content, line)[span.0..span.1];
+    let serial: u32 = text
+        .parse()
+        .map_err(|_| anyhow!("Invalid SOA serial: {}", text))?;
+    let today: u32 = Local::now().format("%Y%m%d00").to_string().parse()?;
+    let new_serial = next_serial(serial, today);
+    debug!("bump SOA serial {serial} -> {new_serial}");
+    Ok(Edit {
+        line,
+        span,
+        text: new_serial.to_string(),
+    })
+}
+
+fn parse_hosts(content: &str) -> Vec<HostsEntry> {
+    split_lines(content)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(line_no, line)| {
+            let tokens = tokenize(line, b'#');
+            let (&ip_span, names) = tokens.split_first()?;
+            let ip = line[ip_span.0..ip_span.1].parse().ok()?;
+            Some(HostsEntry {
+                line: line_no,
+                ip,
+                ip_span,
+                names: names
+                    .iter()
+                    .map(|&(start, end)| line[start..end].to_string())
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn apply_edits(content: &str, mut edits: Vec<Edit>) -> String {
+    let mut lines: Vec<String> = split_lines(content).into_iter().map(String::from).collect();
+    // 同一行内从后往前替换，避免前面的替换影响后面的范围
+    edits.sort_by_key(|edit| std::cmp::Reverse((edit.line, edit.span.0)));
+    for edit in edits {
+        lines[edit.line].replace_range(edit.span.0..edit.span.1, &edit.text);
+    }
+    lines.concat()
+}
+
+fn append_line(content: &str, line: &str) -> String {
+    let mut content = content.to_string();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(line);
+    content
+}
+
+/// 先写入同目录下的临时文件，再rename覆盖，保证读取方不会看到写了一半的文件
+pub(crate) fn write_atomic(path: &Path, content: &str) -> Result<(), Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?
+        .to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{file_name}.ddns.tmp"));
+
+    let result = (|| -> Result<(), Error> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(anyhow!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+fn run_reload_command(reload_command: &str, domain: &str, new_ip: &str) -> Result<(), Error> {
+    info!("Executing reload command for domain {domain}: {reload_command}");
+
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("powershell");
+        cmd.creation_flags(0x08000000)
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-Command")
+            .arg(reload_command);
+        cmd
+    };
+
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c").arg(reload_command);
+        cmd
+    };
+
+    let output = cmd
+        .env("DOMAIN", domain)
+        .env("NEW_IP", new_ip)
+        .output()
+        .map_err(|e| anyhow!("Failed to execute reload command: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Reload command failed with exit code {:?}: {}",
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的file provider配置
+#[derive(Deserialize, Debug)]
+pub struct FileConfig {
+    /// zone文件或hosts文件路径
+    pub file_path: PathBuf,
+    /// 文件格式，未设置时为hosts
+    #[serde(default)]
+    pub file_format: FileFormat,
+    /// zone文件中没有 $ORIGIN 时使用的origin
+    #[serde(default)]
+    pub file_origin: Option<String>,
+    /// 文件写入后执行的reload指令
+    #[serde(default)]
+    pub file_reload_command: Option<String>,
+}
+
+impl ProviderConfig for FileConfig {
+    const NAME: &'static str = "file";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        let provider =
+            FileProvider::new(self.file_path, self.file_format, context.domain.to_string())
+                .origin(self.file_origin)
+                .reload_command(self.file_reload_command)
+                .record_type(get_record_type(context.current_ip));
+        Ok(Box::new(provider))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZONE: &str = "$ORIGIN example.com.
+$TTL 300
+@   IN SOA ns1 hostmaster (
+        2024010101 ; serial
+        3600 600 86400 60 )
+    IN NS ns1
+ns1 IN A 10.0.0.1
+home 60 IN A 1.2.3.4 ; ddns
+txt IN TXT \"a ; b\"
+";
+
+    #[test]
+    fn test_zone_modify() {
+        let zone = parse_zone(ZONE, None).unwrap();
+        assert_eq!(zone.origin.as_deref(), Some("example.com"));
+        let (line, span) = zone.serial.unwrap();
+        assert_eq!(&line_at(ZONE, line)[span.0..span.1], "2024010101");
+
+        let entry = zone
+            .entries
+            .iter()
+            .find(|e| e.owner == "home.example.com")
+            .unwrap();
+        assert_eq!(entry.record_type, "A");
+        let edits = vec![
+            Edit {
+                line: entry.line,
+                span: entry.rdata[0],
+                text: "5.6.7.8".to_string(),
+            },
+            Edit {
+                line,
+                span,
+                text: next_serial(2024010101, 2026101800).to_string(),
+            },
+        ];
+        let updated = apply_edits(ZONE, edits);
+        assert!(updated.contains("home 60 IN A 5.6.7.8 ; ddns\n"));
+        assert!(updated.contains("        2026101800 ; serial\n"));
+        assert!(updated.contains("txt IN TXT \"a ; b\"\n"));
+        assert_eq!(next_serial(2026101800, 2026101800), 2026101801);
+        assert_eq!(next_serial(42, 2026101800), 43);
+    }
+
+    #[test]
+    fn test_hosts_parse() {
+        let hosts = "127.0.0.1 localhost\n# 1.1.1.1 home.example.com\n1.2.3.4\thome.example.com home # ddns\n";
+        let entries = parse_hosts(hosts);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].names, vec!["home.example.com", "home"]);
+        let updated = apply_edits(
+            hosts,
+            vec![Edit {
+                line: entries[1].line,
+                span: entries[1].ip_span,
+                text: "5.6.7.8".to_string(),
+            }],
+        );
+        assert!(updated.ends_with("5.6.7.8\thome.example.com home # ddns\n"));
+    }
+
+    #[test]
+    fn test_update_by_family() {
+        let dir = std::env::temp_dir().join(format!("ddns-file-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // IPv6更新新增AAAA行，保留原有的A行
+        let hosts_path = dir.join("hosts");
+        fs::write(&hosts_path, "1.2.3.4\thome.example.com\n").unwrap();
+        let provider = |path: &Path, format, ip| {
+            FileProvider::new(path.to_path_buf(), format, "home.example.com".to_string())
+                .record_type(get_record_type(ip))
+        };
+        let hosts = provider(&hosts_path, FileFormat::Hosts, "2001:db8::1");
+        assert!(hosts.get_record().unwrap().is_none());
+        hosts.update_dns_record("2001:db8::1").unwrap();
+        hosts.update_dns_record("2001:db8::2").unwrap();
+        assert_eq!(
+            fs::read_to_string(&hosts_path).unwrap(),
+            "1.2.3.4\thome.example.com\n2001:db8::2\thome.example.com\n"
+        );
+        let record = provider(&hosts_path, FileFormat::Hosts, "1.2.3.4")
+            .get_record()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (record.value.as_str(), record.record_type.as_str()),
+            ("1.2.3.4", "A")
+        );
+
+        let zone_path = dir.join("example.com.zone");
+        fs::write(&zone_path, ZONE).unwrap();
+        let zone = provider(&zone_path, FileFormat::Zone, "2001:db8::1");
+        zone.update_dns_record("2001:db8::1").unwrap();
+        zone.update_dns_record("2001:db8::2").unwrap();
+        let content = fs::read_to_string(&zone_path).unwrap();
+        assert!(content.contains("home 60 IN A 1.2.3.4 ; ddns\n"));
+        assert!(content.ends_with("home\t60\tIN\tAAAA\t2001:db8::2\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
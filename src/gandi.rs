@@ -0,0 +1,223 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const GANDI_LIVEDNS_API: &str = "https://api.gandi.net/v5/livedns";
+
+// ========== Gandi LiveDNS 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GandiDomain {
+    fqdn: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GandiRrset {
+    rrset_name: String,
+    rrset_type: String,
+    rrset_values: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct GandiRrsetRequest {
+    rrset_values: Vec<String>,
+    rrset_ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct GandiErrorResponse {
+    message: String,
+}
+
+// ========== Gandi LiveDNS Provider 实现 ==========
+
+pub struct GandiProvider {
+    api: String,
+    token: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl GandiProvider {
+    /// token 为 Gandi 的 Personal Access Token
+    pub fn new(token: String, record_name: String) -> Self {
+        GandiProvider {
+            api: GANDI_LIVEDNS_API.to_string(),
+            token,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 读写的rrset类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 获取(托管域名, 相对记录名)
+    fn get_zone(&self) -> Result<(String, String), Error> {
+        let client = crate::http_client()?;
+        let response = client
+            .get(format!("{}/domains", self.api))
+            .bearer_auth(&self.token)
+            .send()
+            .map_err(|e| anyhow!("Failed to query domain list: {}", e))?;
+        let text = Self::check_response(response)?;
+        let domains: Vec<GandiDomain> = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse domain list response: {}", e))?;
+
+        domains
+            .into_iter()
+            .filter_map(|d| relative_name(&self.record_name, &d.fqdn).map(|name| (d.fqdn, name)))
+            .max_by_key(|(fqdn, _)| fqdn.len())
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))
+    }
+
+    /// 检查HTTP状态码，非2xx时解析Gandi的错误信息
+    fn check_response(response: reqwest::blocking::Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<GandiErrorResponse>(&text)
+                .map(|e| e.message)
+                .unwrap_or(text);
+            Err(anyhow!("Gandi API error ({}): {}", status, message))
+        }
+    }
+
+    /// PUT rrset，不存在时创建，存在时整体替换
+    fn put_rrset(&self, current_ip: &str) -> Result<(), Error> {
+        let (zone, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+        let res = client
+            .put(format!(
+                "{}/domains/{zone}/records/{name}/{}",
+                self.api,
+                get_record_type(current_ip)
+            ))
+            .bearer_auth(&self.token)
+            .json(&GandiRrsetRequest {
+                rrset_values: vec![current_ip.to_string()],
+                rrset_ttl: 300,
+            })
+            .send()?;
+        let text = Self::check_response(res)?;
+        debug!("gandi put rrset result: {text}");
+        Ok(())
+    }
+}
+
+impl DnsProvider for GandiProvider {
+    /// 获取DNS记录，只查询record_type类型的rrset
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (zone, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let res = client
+            .get(format!(
+                "{}/domains/{zone}/records/{name}/{}",
+                self.api, self.record_type
+            ))
+            .bearer_auth(&self.token)
+            .send()?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let text = Self::check_response(res)?;
+        let result: serde_json::Result<GandiRrset> = serde_json::from_str(&text);
+
+        match result {
+            Ok(rrset) => {
+                info!("current gandi rrset is {:?}", rrset);
+                Ok(Some(DnsRecord {
+                    id: format!("{}/{}", rrset.rrset_name, rrset.rrset_type),
+                    value: rrset.rrset_values.first().cloned().unwrap_or_default(),
+                    name: rrset.rrset_name,
+                    record_type: rrset.rrset_type,
+                }))
+            }
+            Err(err) => {
+                warn!("error parse gandi result: {text}");
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// 修改DNS记录，PUT只替换同一类型的rrset
+    fn modify_record(&self, current_ip: &str, _record: &DnsRecord) -> Result<(), Error> {
+        self.put_rrset(current_ip)
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        self.put_rrset(current_ip)
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Gandi LiveDNS配置
+#[derive(Deserialize, Debug)]
+pub struct GandiConfig {
+    /// Gandi Personal Access Token
+    pub gandi_token: String,
+}
+
+impl ProviderConfig for GandiConfig {
+    const NAME: &'static str = "gandi";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            GandiProvider::new(self.gandi_token, context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        const DOMAINS: &str = r#"[{"fqdn": "example.com"}]"#;
+        let (address, handle) = serve_http(vec![
+            DOMAINS,
+            r#"{"rrset_name": "home", "rrset_type": "AAAA", "rrset_values": ["2001:db8::1"]}"#,
+            DOMAINS,
+            "{}",
+        ]);
+        let mut provider = GandiProvider::new("token".to_string(), "home.example.com".to_string())
+            .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // 只读取和替换AAAA rrset，不删除A rrset
+        let result = provider.update_dns_record("2001:db8::2").unwrap();
+        assert!(matches!(
+            result,
+            DnsUpdateResult::Changed { old_ip, .. } if old_ip == "2001:db8::1"
+        ));
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(
+            requests[1]
+                .0
+                .starts_with("GET /domains/example.com/records/home/AAAA ")
+        );
+        assert!(
+            requests[3]
+                .0
+                .starts_with("PUT /domains/example.com/records/home/AAAA ")
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const HETZNER_API: &str = "https://dns.hetzner.com/api/v1";
+
+/// 查询zone列表时每页的数量 (API允许的最大值)
+const ZONE_PAGE_SIZE: usize = 100;
+
+// 全局的 Hetzner Zone 缓存: api_token -> zone_name -> zone_id
+static HETZNER_ZONE_CACHE: LazyLock<Mutex<HashMap<String, HashMap<String, String>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// ========== Hetzner 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HetznerZoneListResponse {
+    zones: Vec<HetznerZone>,
+    #[serde(default)]
+    meta: Option<HetznerMeta>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HetznerMeta {
+    pagination: HetznerPagination,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HetznerPagination {
+    last_page: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HetznerZone {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HetznerRecordListResponse {
+    records: Vec<HetznerRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct HetznerRecord {
+    id: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    value: String,
+    zone_id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HetznerRecordRequest {
+    zone_id: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    value: String,
+    ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct HetznerErrorResponse {
+    error: HetznerError,
+}
+
+#[derive(Deserialize, Debug)]
+struct HetznerError {
+    message: String,
+    code: i32,
+}
+
+// ========== Hetzner Provider 实现 ==========
+
+pub struct HetznerProvider {
+    api: String,
+    api_token: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl HetznerProvider {
+    pub fn new(api_token: String, record_name: String) -> Self {
+        HetznerProvider {
+            api: HETZNER_API.to_string(),
+            api_token,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 按该类型筛选zone中的记录，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 分页获取账户下的全部zone
+    fn list_zones(&self) -> Result<Vec<HetznerZone>, Error> {
+        let client = crate::http_client()?;
+        let mut zones = Vec::new();
+        for page in 1.. {
+            let response = client
+                .get(format!(
+                    "{}/zones?page={page}&per_page={ZONE_PAGE_SIZE}",
+                    self.api
+                ))
+                .header("Auth-API-Token", &self.api_token)
+                .send()
+                .map_err(|e| anyhow!("Failed to query zone list: {}", e))?;
+            let text = Self::check_response(response)?;
+            let zone_list: HetznerZoneListResponse = serde_json::from_str(&text)
+                .map_err(|e| anyhow!("Failed to parse zone list response: {}", e))?;
+            let count = zone_list.zones.len();
+            zones.extend(zone_list.zones);
+            // 已到最后一页，响应中没有分页信息时以不满一页为最后一页
+            let last = match zone_list.meta {
+                Some(meta) => page >= meta.pagination.last_page,
+                None => count < ZONE_PAGE_SIZE,
+            };
+            if last {
+                break;
+            }
+        }
+        Ok(zones)
+    }
+
+    /// 获取(zone_id, 相对记录名)，zone_id优先从缓存读取
+    fn get_zone(&self) -> Result<(String, String), Error> {
+        // 先尝试从缓存读取
+        {
+            let cache = HETZNER_ZONE_CACHE.lock().unwrap();
+            if let Some(token_cache) = cache.get(&self.api_token) {
+                let cached = token_cache
+                    .iter()
+                    .filter_map(|(zone_name, zone_id)| {
+                        relative_name(&self.record_name, zone_name)
+                            .map(|name| (zone_name, zone_id, name))
+                    })
+                    .max_by_key(|(zone_name, _, _)| zone_name.len());
+                if let Some((zone_name, zone_id, name)) = cached {
+                    debug!(
+                        "Using cached hetzner zone_id for {}: {}",
+                        zone_name, zone_id
+                    );
+                    return Ok((zone_id.clone(), name));
+                }
+            }
+        }
+
+        // 缓存未命中，调用API查询
+        let (zone, name) = self
+            .list_zones()?
+            .into_iter()
+            .filter_map(|zone| {
+                relative_name(&self.record_name, &zone.name).map(|name| (zone, name))
+            })
+            .max_by_key(|(zone, _)| zone.name.len())
+            .ok_or_else(|| anyhow!("No zone found for domain: {}", self.record_name))?;
+        debug!("Found hetzner zone_id for {}: {}", zone.name, zone.id);
+
+        // 存入缓存
+        {
+            let mut cache = HETZNER_ZONE_CACHE.lock().unwrap();
+            cache
+                .entry(self.api_token.clone())
+                .or_default()
+                .insert(zone.name, zone.id.clone());
+        }
+
+        Ok((zone.id, name))
+    }
+
+    /// 检查HTTP状态码，非2xx时解析Hetzner的错误信息
+    fn check_response(response: reqwest::blocking::Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<HetznerErrorResponse>(&text)
+                .map(|e| format!("{}: {}", e.error.code, e.error.message))
+                .unwrap_or(text);
+            Err(anyhow!("Hetzner API error ({}): {}", status, message))
+        }
+    }
+}
+
+impl DnsProvider for HetznerProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (zone_id, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let res = client
+            .get(format!("{}/records?zone_id={zone_id}", self.api))
+            .header("Auth-API-Token", &self.api_token)
+            .send()?;
+        let text = Self::check_response(res)?;
+        let result: serde_json::Result<HetznerRecordListResponse> = serde_json::from_str(&text);
+
+        match result {
+            Ok(response) => {
+                let record = response
+                    .records
+                    .into_iter()
+                    .find(|record| record.name == name && record.record_type == self.record_type);
+                match record {
+                    Some(record) => {
+                        info!("current hetzner record is {:?}", record);
+                        Ok(Some(DnsRecord {
+                            id: record.id,
+                            name: record.name,
+                            value: record.value,
+                            record_type: record.record_type,
+                        }))
+                    }
+                    None => Ok(None),
+                }
+            }
+            Err(err) => {
+                warn!("error parse hetzner result: {text}");
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let (zone_id, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let update_request = HetznerRecordRequest {
+            zone_id,
+            record_type: record.record_type.clone(),
+            name,
+            value: current_ip.to_string(),
+            ttl: 60,
+        };
+
+        let res = client
+            .put(format!("{}/records/{}", self.api, record.id))
+            .header("Auth-API-Token", &self.api_token)
+            .json(&update_request)
+            .send()?;
+        let text = Self::check_response(res)?;
+        debug!("hetzner modify result: {text}");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let (zone_id, name) = self.get_zone()?;
+        let client = crate::http_client()?;
+
+        let create_request = HetznerRecordRequest {
+            zone_id,
+            record_type: get_record_type(current_ip).to_string(),
+            name,
+            value: current_ip.to_string(),
+            ttl: 60,
+        };
+
+        let res = client
+            .post(format!("{}/records", self.api))
+            .header("Auth-API-Token", &self.api_token)
+            .json(&create_request)
+            .send()?;
+        let text = Self::check_response(res)?;
+        debug!("hetzner add result: {text}");
+        Ok(())
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Hetzner DNS配置
+#[derive(Deserialize, Debug)]
+pub struct HetznerConfig {
+    /// Hetzner DNS API Token
+    pub hetzner_token: String,
+}
+
+impl ProviderConfig for HetznerConfig {
+    const NAME: &'static str = "hetzner";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            HetznerProvider::new(self.hetzner_token, context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        let (address, handle) = serve_http(vec![
+            r#"{"zones": [{"id": "z1", "name": "example.com"}]}"#,
+            r#"{"records": [
+                {"id": "r1", "type": "A", "name": "home", "value": "1.2.3.4", "zone_id": "z1"},
+                {"id": "r2", "type": "AAAA", "name": "home", "value": "2001:db8::1", "zone_id": "z1"}
+            ]}"#,
+            "{}",
+        ]);
+        let mut provider =
+            HetznerProvider::new("test-token".to_string(), "home.example.com".to_string())
+                .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // 只修改AAAA记录，A记录保持不变
+        let result = provider.update_dns_record("2001:db8::2").unwrap();
+        assert!(matches!(
+            result,
+            DnsUpdateResult::Changed { old_ip, record_id: Some(id) }
+                if old_ip == "2001:db8::1" && id == "r2"
+        ));
+        let requests = handle.join().unwrap();
+        assert!(requests[2].0.starts_with("PUT /records/r2 "));
+        assert!(requests[2].1.contains(r#""type":"AAAA""#));
+    }
+
+    #[test]
+    fn test_zone_pages() {
+        let (address, handle) = serve_http(vec![
+            r#"{"zones": [{"id": "z1", "name": "other.com"}],
+                "meta": {"pagination": {"page": 1, "per_page": 100, "last_page": 2}}}"#,
+            r#"{"zones": [{"id": "z2", "name": "example.com"}],
+                "meta": {"pagination": {"page": 2, "per_page": 100, "last_page": 2}}}"#,
+        ]);
+        let mut provider =
+            HetznerProvider::new("paged-token".to_string(), "home.example.com".to_string());
+        provider.api = format!("http://{address}");
+
+        assert_eq!(
+            provider.get_zone().unwrap(),
+            ("z2".to_string(), "home".to_string())
+        );
+        let requests = handle.join().unwrap();
+        assert!(requests[0].0.starts_with("GET /zones?page=1&per_page=100 "));
+        assert!(requests[1].0.starts_with("GET /zones?page=2&per_page=100 "));
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{Error, anyhow};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::schedule::parse_duration;
+
+// ========== IP变化和更新历史 ==========
+
+/// 历史事件的类型
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEvent {
+    /// 检测到域名应当指向的IP发生变化
+    IpChange,
+    /// 调用provider更新了记录
+    DnsUpdate,
+}
+
+/// 更新的结果
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failed,
+}
+
+/// 一条历史记录
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub time: DateTime<Local>,
+    pub event: HistoryEvent,
+    /// 完整域名
+    pub domain: String,
+    /// 更新使用的provider，仅dns_update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 变化前的IP，首次检测或新建记录时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_ip: Option<String>,
+    pub new_ip: String,
+    /// 更新的结果，仅dns_update
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<Outcome>,
+    /// 更新失败的错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 以JSON Lines格式保存的历史文件，每行一条记录，只追加不修改
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        History { path: path.into() }
+    }
+
+    /// 追加一条记录
+    pub fn append(&self, entry: &HistoryEntry) -> Result<(), Error> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| anyhow!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// 按时间顺序读取记录，可按域名 (不区分大小写) 和起始时间过滤，无法解析的行跳过
+    pub fn query(
+        &self,
+        domain: Option<&str>,
+        since: Option<DateTime<Local>>,
+    ) -> Result<Vec<HistoryEntry>, Error> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", self.path.display(), e)),
+        };
+        let entries = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(
+                |(i, line)| match serde_json::from_str::<HistoryEntry>(line) {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        warn!("Skip line {} of {}: {}", i + 1, self.path.display(), e);
+                        None
+                    }
+                },
+            )
+            .filter(|entry| domain.is_none_or(|domain| entry.domain.eq_ignore_ascii_case(domain)))
+            .filter(|entry| since.is_none_or(|since| entry.time >= since))
+            .collect();
+        Ok(entries)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 解析 --since 参数：RFC 3339 时间、本地日期 "2024-06-01"、本地时间 "2024-06-01 08:00"，
+/// 或时长 (如 "7d"、"12h") 表示从现在往前推
+pub fn parse_since(text: &str) -> Result<DateTime<Local>, Error> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Local));
+    }
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        });
+    if let Some(local) = local {
+        return Local
+            .from_local_datetime(&local)
+            .earliest()
+            .ok_or_else(|| anyhow!("{text:?} does not exist in the local time zone"));
+    }
+    let ago = parse_duration(text).map_err(|_| {
+        anyhow!(
+            "invalid time {text:?}, expected a date like \"2024-06-01\" or a duration like \"7d\""
+        )
+    })?;
+    chrono::Duration::from_std(ago)
+        .ok()
+        .and_then(|ago| Local::now().checked_sub_signed(ago))
+        .ok_or_else(|| anyhow!("{text:?} is too far in the past"))
+}
+
+/// 表格和CSV中的列
+const COLUMNS: [&str; 8] = [
+    "time", "event", "domain", "provider", "old_ip", "new_ip", "outcome", "error",
+];
+
+impl HistoryEntry {
+    /// 各列的文本，time为本地时间
+    fn fields(&self, time_format: &str) -> [String; 8] {
+        let event = match self.event {
+            HistoryEvent::IpChange => "ip_change",
+            HistoryEvent::DnsUpdate => "dns_update",
+        };
+        let outcome = match self.outcome {
+            Some(Outcome::Success) => "success",
+            Some(Outcome::Failed) => "failed",
+            None => "",
+        };
+        [
+            self.time.format(time_format).to_string(),
+            event.to_string(),
+            self.domain.clone(),
+            self.provider.clone().unwrap_or_default(),
+            self.old_ip.clone().unwrap_or_default(),
+            self.new_ip.clone(),
+            outcome.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// 对齐的文本表格，每列宽度取最长的值
+pub fn format_table(entries: &[HistoryEntry]) -> String {
+    let header = COLUMNS.map(|column| column.to_uppercase());
+    let rows: Vec<[String; 8]> = entries
+        .iter()
+        .map(|entry| entry.fields("%Y-%m-%d %H:%M:%S"))
+        .collect();
+    let widths: Vec<usize> = (0..COLUMNS.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, &width)| format!("{value:<width$}"))
+                .collect();
+            format!("{}\n", line.join("  ").trim_end())
+        })
+        .collect()
+}
+
+/// RFC 4180 格式的CSV，time为RFC 3339格式
+pub fn format_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = format!("{}\n", COLUMNS.join(","));
+    for entry in entries {
+        let fields = entry.fields("%Y-%m-%dT%H:%M:%S%:z");
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let dir = std::env::temp_dir().join(format!("ddns-history-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let history = History::new(dir.join("history.jsonl"));
+        let _ = fs::remove_file(history.path());
+        assert!(history.query(None, None).unwrap().is_empty());
+
+        let at = |text: &str| parse_since(text).unwrap();
+        let change = HistoryEntry {
+            time: at("2024-06-01T08:00:00+08:00"),
+            event: HistoryEvent::IpChange,
+            domain: "home.example.com".to_string(),
+            provider: None,
+            old_ip: Some("1.2.3.4".to_string()),
+            new_ip: "5.6.7.8".to_string(),
+            outcome: None,
+            error: None,
+        };
+        let update = HistoryEntry {
+            time: at("2024-06-02T08:00:00+08:00"),
+            event: HistoryEvent::DnsUpdate,
+            domain: "vpn.example.com".to_string(),
+            provider: Some("cloudflare".to_string()),
+            outcome: Some(Outcome::Failed),
+            error: Some("Cloudflare API error: 10000, \"Authentication error\"".to_string()),
+            ..change.clone()
+        };
+        history.append(&change).unwrap();
+        history.append(&update).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        assert_eq!(
+            history.query(None, None).unwrap(),
+            [change.clone(), update.clone()]
+        );
+        assert_eq!(
+            history.query(Some("HOME.example.com"), None).unwrap(),
+            std::slice::from_ref(&change)
+        );
+        assert_eq!(
+            history
+                .query(None, Some(at("2024-06-01T12:00:00+08:00")))
+                .unwrap(),
+            std::slice::from_ref(&update)
+        );
+
+        let csv = format_csv(&[update]);
+        assert!(csv.starts_with("time,event,domain,provider,old_ip,new_ip,outcome,error\n"));
+        assert!(csv.ends_with(
+            ",dns_update,vpn.example.com,cloudflare,1.2.3.4,5.6.7.8,failed,\"Cloudflare API error: 10000, \"\"Authentication error\"\"\"\n"
+        ));
+        let table = format_table(&[change]);
+        assert!(table.starts_with("TIME                 EVENT      DOMAIN"));
+        assert_eq!(table.lines().count(), 2);
+
+        assert!(parse_since("2024-06-01").is_ok());
+        assert!(parse_since("7d").unwrap() < Local::now());
+        assert!(parse_since("last week").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
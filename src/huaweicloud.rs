@@ -0,0 +1,447 @@
+use anyhow::{Error, anyhow};
+use chrono::Utc;
+use log::{debug, info, warn};
+use reqwest::Method;
+use ring::{digest, hmac};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type};
+
+const SIGN_ALGORITHM: &str = "SDK-HMAC-SHA256";
+
+// ========== 华为云 DNS 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HuaweiZoneListResponse {
+    zones: Vec<HuaweiZone>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HuaweiZone {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HuaweiRecordsetListResponse {
+    recordsets: Vec<HuaweiRecordset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct HuaweiRecordset {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    records: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct HuaweiRecordsetRequest {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    ttl: u32,
+    records: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct HuaweiErrorResponse {
+    #[serde(alias = "error_code")]
+    code: String,
+    #[serde(alias = "error_msg")]
+    message: String,
+}
+
+// ========== 华为云 DNS Provider 实现 ==========
+
+pub struct HuaweicloudProvider {
+    access_key: String,
+    secret_key: String,
+    endpoint: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl HuaweicloudProvider {
+    /// region 为华为云区域，如 "cn-north-4"，对应endpoint为 dns.{region}.myhuaweicloud.com
+    pub fn new(
+        access_key: String,
+        secret_key: String,
+        region: String,
+        record_name: String,
+    ) -> Self {
+        HuaweicloudProvider {
+            access_key,
+            secret_key,
+            endpoint: format!("https://dns.{region}.myhuaweicloud.com"),
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 按该类型查询记录集，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    fn host(&self) -> &str {
+        self.endpoint
+            .split_once("://")
+            .map_or(self.endpoint.as_str(), |(_, host)| host)
+    }
+
+    /// 转换为以点结尾的规范名称
+    fn canonical(name: &str) -> String {
+        if name.ends_with('.') {
+            name.to_string()
+        } else {
+            format!("{name}.")
+        }
+    }
+
+    /// 发送经过AK/SK签名的请求，返回响应体
+    fn signed_request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<String>,
+    ) -> Result<String, Error> {
+        let host = self.host();
+        let sdk_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let body = body.unwrap_or_default();
+        let canonical_query = canonical_query_string(query);
+
+        let mut headers = vec![("host", host.to_string()), ("x-sdk-date", sdk_date.clone())];
+        if !body.is_empty() {
+            headers.push(("content-type", "application/json".to_string()));
+        }
+        let authorization = sign(
+            &self.access_key,
+            &self.secret_key,
+            method.as_str(),
+            path,
+            &canonical_query,
+            &mut headers,
+            &sdk_date,
+            body.as_bytes(),
+        );
+
+        let mut url = format!("{}{path}", self.endpoint);
+        if !canonical_query.is_empty() {
+            url = format!("{url}?{canonical_query}");
+        }
+        let client = crate::http_client()?;
+        let mut request = client
+            .request(method, url)
+            .header("X-Sdk-Date", &sdk_date)
+            .header("Authorization", authorization);
+        if !body.is_empty() {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        let response = request.send()?;
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<HuaweiErrorResponse>(&text)
+                .map(|e| format!("{}: {}", e.code, e.message))
+                .unwrap_or(text);
+            Err(anyhow!(
+                "Huawei Cloud DNS API error ({}): {}",
+                status,
+                message
+            ))
+        }
+    }
+
+    /// 获取zone id，查询公网zone列表并选择与记录名称匹配的最长后缀
+    fn get_zone_id(&self) -> Result<String, Error> {
+        let record_name = Self::canonical(&self.record_name);
+        let text = self.signed_request(
+            Method::GET,
+            "/v2/zones",
+            &[("type", "public"), ("limit", "500")],
+            None,
+        )?;
+        let zones: HuaweiZoneListResponse = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Failed to parse zone list response: {}", e))?;
+
+        zones
+            .zones
+            .into_iter()
+            .filter(|zone| {
+                record_name == zone.name || record_name.ends_with(&format!(".{}", zone.name))
+            })
+            .max_by_key(|zone| zone.name.len())
+            .map(|zone| {
+                debug!("Found huaweicloud zone for {}: {}", record_name, zone.id);
+                zone.id
+            })
+            .ok_or_else(|| anyhow!("No zone found for domain: {}", self.record_name))
+    }
+}
+
+impl DnsProvider for HuaweicloudProvider {
+    /// 获取DNS记录，DnsRecord.id 格式为 "zone_id/recordset_id"
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let zone_id = self.get_zone_id()?;
+        let record_name = Self::canonical(&self.record_name);
+        let text = self.signed_request(
+            Method::GET,
+            &format!("/v2/zones/{zone_id}/recordsets"),
+            &[("name", record_name.as_str()), ("type", self.record_type)],
+            None,
+        )?;
+        let result: serde_json::Result<HuaweiRecordsetListResponse> = serde_json::from_str(&text);
+
+        match result {
+            Ok(response) => {
+                // name 参数为模糊匹配，需要再精确过滤
+                let recordset = response.recordsets.into_iter().find(|recordset| {
+                    recordset.name == record_name && recordset.record_type == self.record_type
+                });
+                match recordset {
+                    Some(recordset) => {
+                        info!("current huaweicloud recordset is {:?}", recordset);
+                        Ok(Some(DnsRecord {
+                            id: format!("{zone_id}/{}", recordset.id),
+                            name: recordset.name,
+                            value: recordset.records.first().cloned().unwrap_or_default(),
+                            record_type: recordset.record_type,
+                        }))
+                    }
+                    None => Ok(None),
+                }
+            }
+            Err(err) => {
+                warn!("error parse huaweicloud result: {text}");
+                Err(anyhow!(err))
+            }
+        }
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let update_request = HuaweiRecordsetRequest {
+            name: Self::canonical(&self.record_name),
+            record_type: record.record_type.clone(),
+            ttl: 300,
+            records: vec![current_ip.to_string()],
+        };
+        let text = self.signed_request(
+            Method::PUT,
+            &format!("/v2/zones/{}", record.id.replacen('/', "/recordsets/", 1)),
+            &[],
+            Some(serde_json::to_string(&update_request)?),
+        )?;
+        debug!("huaweicloud modify result: {text}");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let zone_id = self.get_zone_id()?;
+        let create_request = HuaweiRecordsetRequest {
+            name: Self::canonical(&self.record_name),
+            record_type: get_record_type(current_ip).to_string(),
+            ttl: 300,
+            records: vec![current_ip.to_string()],
+        };
+        let text = self.signed_request(
+            Method::POST,
+            &format!("/v2/zones/{zone_id}/recordsets"),
+            &[],
+            Some(serde_json::to_string(&create_request)?),
+        )?;
+        debug!("huaweicloud add result: {text}");
+        Ok(())
+    }
+}
+
+// ========== SDK-HMAC-SHA256 签名 ==========
+
+/// 按RFC 3986对字符串进行百分号编码（仅保留非保留字符）
+fn uri_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// 规范查询字符串：参数按名称排序，名称和值均进行编码
+fn canonical_query_string(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k), uri_encode(v)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 计算Authorization头
+/// headers 中的名称需为小写，签名时会按名称排序
+#[allow(clippy::too_many_arguments)]
+fn sign(
+    access_key: &str,
+    secret_key: &str,
+    method: &str,
+    path: &str,
+    canonical_query: &str,
+    headers: &mut [(&str, String)],
+    sdk_date: &str,
+    body: &[u8],
+) -> String {
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+
+    // 规范URI需要以 "/" 结尾
+    let mut canonical_uri = path
+        .split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    if !canonical_uri.ends_with('/') {
+        canonical_uri.push('/');
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n{canonical_query}\n{canonical_headers}\n{signed_headers}\n{}",
+        hex_encode(digest::digest(&digest::SHA256, body).as_ref())
+    );
+    let string_to_sign = format!(
+        "{SIGN_ALGORITHM}\n{sdk_date}\n{}",
+        hex_encode(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret_key.as_bytes());
+    let signature = hex_encode(hmac::sign(&key, string_to_sign.as_bytes()).as_ref());
+
+    format!(
+        "{SIGN_ALGORITHM} Access={access_key}, SignedHeaders={signed_headers}, Signature={signature}"
+    )
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的华为云DNS配置
+#[derive(Deserialize, Debug)]
+pub struct HuaweicloudConfig {
+    /// Access Key (AK)
+    pub huaweicloud_access_key: String,
+    /// Secret Key (SK)
+    pub huaweicloud_secret_key: String,
+    /// 区域，未设置时为 "cn-north-4"
+    #[serde(default = "default_region")]
+    pub huaweicloud_region: String,
+}
+
+fn default_region() -> String {
+    "cn-north-4".to_string()
+}
+
+impl ProviderConfig for HuaweicloudConfig {
+    const NAME: &'static str = "huaweicloud";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            HuaweicloudProvider::new(
+                self.huaweicloud_access_key,
+                self.huaweicloud_secret_key,
+                self.huaweicloud_region,
+                context.domain.to_string(),
+            )
+            .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_sign() {
+        let mut headers = vec![
+            ("x-sdk-date", "20240101T000000Z".to_string()),
+            ("host", "dns.cn-north-4.myhuaweicloud.com".to_string()),
+        ];
+        let canonical_query = canonical_query_string(&[("type", "public"), ("limit", "500")]);
+        assert_eq!(canonical_query, "limit=500&type=public");
+
+        let authorization = sign(
+            "AK",
+            "SK",
+            "GET",
+            "/v2/zones",
+            &canonical_query,
+            &mut headers,
+            "20240101T000000Z",
+            b"",
+        );
+        assert_eq!(
+            authorization,
+            "SDK-HMAC-SHA256 Access=AK, SignedHeaders=host;x-sdk-date, Signature=ed091beb13b1616854cd240eb30dadf3708af8761241e72e03f5377e662b4c8c"
+        );
+    }
+
+    #[test]
+    fn test_get_record() {
+        let (address, handle) = serve_http(vec![
+            r#"{"zones": [{"id": "z1", "name": "example.com."}]}"#,
+            r#"{"recordsets": [
+                {"id": "r1", "name": "home.example.com.", "type": "A", "records": ["1.2.3.4"]},
+                {"id": "r2", "name": "home.example.com.", "type": "AAAA", "records": ["2001:db8::1"]}
+            ]}"#,
+        ]);
+        let mut provider = HuaweicloudProvider::new(
+            "AK".to_string(),
+            "SK".to_string(),
+            "cn-north-4".to_string(),
+            "home.example.com".to_string(),
+        )
+        .record_type("AAAA");
+        provider.endpoint = format!("http://{address}");
+
+        // 只选择当前地址族的记录集
+        let record = provider.get_record().unwrap().unwrap();
+        assert_eq!(record.id, "z1/r2");
+        assert_eq!(record.value, "2001:db8::1");
+        let requests = handle.join().unwrap();
+        assert!(
+            requests[1]
+                .0
+                .starts_with("GET /v2/zones/z1/recordsets?name=home.example.com.&type=AAAA ")
+        );
+    }
+}
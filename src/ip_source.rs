@@ -0,0 +1,222 @@
+use anyhow::{Error, anyhow};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::bind::Bind;
+
+mod dns;
+mod local;
+mod natpmp;
+mod router;
+mod stun;
+mod upnp;
+
+pub use dns::{DnsService, DnsSourceConfig};
+pub use natpmp::NatPmpConfig;
+pub use router::{MikrotikConfig, UbusConfig};
+pub use stun::StunConfig;
+pub use upnp::UpnpConfig;
+
+/// 向网关查询时等待响应的超时时间
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 全局静态HTTP客户端，禁用代理
+static HTTP_CLIENT: LazyLock<reqwest::blocking::Client> = LazyLock::new(|| {
+    reqwest::blocking::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+// ========== IP 来源 ==========
+
+/// 获取当前IP的方式，配置文件中写作 `ip_source = { upnp = {} }` 的形式
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum IpSource {
+    /// 访问返回IP的HTTP地址，如 "http://whatismyip.akamai.com"
+    Http(String),
+    /// 通过UPnP IGD向网关查询WAN地址
+    Upnp(UpnpConfig),
+    /// 通过NAT-PMP向网关查询WAN地址
+    #[serde(rename = "natpmp")]
+    NatPmp(NatPmpConfig),
+    /// 通过PCP向网关查询WAN地址
+    Pcp(NatPmpConfig),
+    /// 通过DNS查询获取地址
+    Dns(DnsSourceConfig),
+    /// 通过STUN Binding请求获取地址
+    Stun(StunConfig),
+    /// 执行命令，标准输出即为IP (Linux/macOS 使用 bash，Windows 使用 powershell)
+    Command(String),
+    /// 读取文件，文件内容即为IP
+    File(PathBuf),
+    /// 通过OpenWrt的ubus JSON-RPC查询WAN接口地址
+    Ubus(UbusConfig),
+    /// 通过MikroTik RouterOS的REST API查询WAN接口地址
+    Mikrotik(MikrotikConfig),
+}
+
+impl IpSource {
+    /// 获取当前IP，网络请求通过bind绑定的网卡和源地址发出 (command和file不涉及网络请求，不受影响)
+    pub fn current_ip(&self, bind: &Bind) -> Result<String, Error> {
+        let ip = match self {
+            IpSource::Http(url) => http_ip(url, bind)?,
+            IpSource::Upnp(config) => upnp::external_ip(config, bind)?,
+            IpSource::NatPmp(config) => natpmp::natpmp_external_ip(config, bind)?,
+            IpSource::Pcp(config) => natpmp::pcp_external_ip(config, bind)?,
+            IpSource::Dns(config) => dns::dns_ip(config, bind)?,
+            IpSource::Stun(config) => stun::stun_ip(config, bind)?,
+            IpSource::Command(command) => local::command_ip(command)?,
+            IpSource::File(path) => local::file_ip(path)?,
+            IpSource::Ubus(config) => router::ubus_ip(config, bind)?,
+            IpSource::Mikrotik(config) => router::mikrotik_ip(config, bind)?,
+        };
+        Ok(ip.to_string())
+    }
+
+    /// 校验配置，在加载配置文件时调用
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            IpSource::Http(url) => check_url(url),
+            IpSource::Upnp(config) => config.location.as_deref().map_or(Ok(()), check_url),
+            IpSource::Ubus(config) => check_url(&config.url),
+            IpSource::Mikrotik(config) => check_url(&config.url),
+            IpSource::Stun(config) if config.servers.is_empty() => {
+                Err(anyhow!("stun servers cannot be empty"))
+            }
+            IpSource::Command(command) if command.trim().is_empty() => {
+                Err(anyhow!("ip source command cannot be empty"))
+            }
+            IpSource::File(path) if path.as_os_str().is_empty() => {
+                Err(anyhow!("ip source file cannot be empty"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 检查是否为合法的HTTP地址
+fn check_url(url: &str) -> Result<(), Error> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("invalid url {url}: {e}"))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(anyhow!("unsupported url scheme {scheme} in {url}")),
+    }
+}
+
+impl fmt::Display for IpSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpSource::Http(url) => write!(f, "{url}"),
+            IpSource::Upnp(_) => write!(f, "UPnP IGD"),
+            IpSource::NatPmp(_) => write!(f, "NAT-PMP"),
+            IpSource::Pcp(_) => write!(f, "PCP"),
+            IpSource::Dns(config) => write!(f, "DNS ({:?})", config.service),
+            IpSource::Stun(_) => write!(f, "STUN"),
+            IpSource::Command(command) => write!(f, "command `{command}`"),
+            IpSource::File(path) => write!(f, "{}", path.display()),
+            IpSource::Ubus(config) => write!(f, "ubus {}", config.url),
+            IpSource::Mikrotik(config) => write!(f, "RouterOS {}", config.url),
+        }
+    }
+}
+
+/// 解析查询结果中的IP地址，忽略首尾空白
+pub fn parse_ip(text: &str) -> Result<IpAddr, Error> {
+    let text = text.trim();
+    text.parse()
+        .map_err(|_| anyhow!("invalid IP address: {:?}", text))
+}
+
+/// 获取HTTP客户端，设置了绑定时创建绑定到对应网卡和源地址的客户端
+fn http_client(bind: &Bind) -> Result<reqwest::blocking::Client, Error> {
+    if bind.is_unbound() {
+        return Ok(HTTP_CLIENT.clone());
+    }
+    Ok(bind
+        .client_builder(reqwest::blocking::Client::builder().no_proxy())
+        .build()?)
+}
+
+/// 访问HTTP地址，响应内容即为当前IP
+fn http_ip(url: &str, bind: &Bind) -> Result<IpAddr, Error> {
+    let text = http_client(bind)?
+        .get(url)
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.text())
+        .map_err(|e| anyhow!("failed to query {url}: {e}"))?;
+    parse_ip(&text)
+}
+
+/// 生成随机字节，用于请求的ID和nonce
+fn rand_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut bytes)
+        .expect("failed to generate random bytes");
+    bytes
+}
+
+// ========== IPv6 前缀 ==========
+
+/// 获取网卡上的全局单播IPv6地址，忽略回环、链路本地 (fe80::/10) 和 ULA (fc00::/7) 地址
+pub fn interface_ipv6(interface: &str) -> Result<Ipv6Addr, Error> {
+    let addrs = if_addrs::get_if_addrs()
+        .map_err(|e| anyhow!("Failed to list network interfaces: {}", e))?;
+    addrs
+        .into_iter()
+        .filter(|iface| iface.name == interface)
+        .filter_map(|iface| match iface.ip() {
+            IpAddr::V6(ip) if is_global_ipv6(&ip) => Some(ip),
+            _ => None,
+        })
+        .inspect(|ip| debug!("Found global IPv6 address on {interface}: {ip}"))
+        .next()
+        .ok_or_else(|| anyhow!("No global IPv6 address found on interface {}", interface))
+}
+
+/// 是否为全局单播IPv6地址 (2000::/3)
+fn is_global_ipv6(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xe000 == 0x2000
+}
+
+/// 用prefix的前prefix_len位作为前缀，interface_id的其余位作为接口标识，组合成完整地址
+/// 例如: ("2001:db8:1:2::abcd", 64, "::1234:5678") -> "2001:db8:1:2::1234:5678"
+pub fn combine_prefix(prefix: Ipv6Addr, prefix_len: u8, interface_id: Ipv6Addr) -> Ipv6Addr {
+    let mask = u128::MAX
+        .checked_shl(128 - u32::from(prefix_len.min(128)))
+        .unwrap_or(0);
+    Ipv6Addr::from((u128::from(prefix) & mask) | (u128::from(interface_id) & !mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_combine_prefix() {
+        let prefix: Ipv6Addr = "2001:db8:1:2::abcd".parse().unwrap();
+        let iid: Ipv6Addr = "::1234:5678".parse().unwrap();
+        assert_eq!(
+            combine_prefix(prefix, 64, iid),
+            "2001:db8:1:2::1234:5678".parse::<Ipv6Addr>().unwrap()
+        );
+        // /56 前缀时接口标识可以带子网号
+        let iid: Ipv6Addr = "::3:0:0:0:1".parse().unwrap();
+        assert_eq!(
+            combine_prefix(prefix, 56, iid),
+            "2001:db8:1:3::1".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(combine_prefix(prefix, 0, iid), iid);
+        assert_eq!(combine_prefix(prefix, 128, iid), prefix);
+        assert!(is_global_ipv6(&prefix));
+        assert!(!is_global_ipv6(&"fe80::1".parse().unwrap()));
+        assert!(!is_global_ipv6(&"fd00::1".parse().unwrap()));
+    }
+}
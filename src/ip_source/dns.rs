@@ -0,0 +1,333 @@
+use anyhow::{Error, anyhow};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use super::rand_bytes;
+use crate::bind::Bind;
+
+// ========== DNS 查询相关结构 ==========
+
+/// 等待DNS响应的超时时间
+const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+
+const TYPE_A: u16 = 1;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const CLASS_CH: u16 = 3;
+/// DNS报文头的长度
+const HEADER_LEN: usize = 12;
+
+/// 通过DNS查询返回客户端地址的服务
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsService {
+    /// 向 resolver1.opendns.com 查询 myip.opendns.com 的 A/AAAA 记录
+    #[default]
+    Opendns,
+    /// 向 ns1.google.com 查询 o-o.myaddr.l.google.com 的 TXT 记录
+    Google,
+    /// 向 1.1.1.1 查询 whoami.cloudflare 的 CH 类 TXT 记录
+    Cloudflare,
+}
+
+/// DNS 查询配置
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DnsSourceConfig {
+    /// 查询的服务，默认为 "opendns"
+    pub service: DnsService,
+    /// 是否获取IPv6地址，为true时通过服务的IPv6地址查询
+    pub ipv6: bool,
+    /// 覆盖服务默认的DNS服务器地址 (如 "208.67.220.220:53")
+    pub resolver: Option<SocketAddr>,
+}
+
+/// 一次DNS查询
+struct DnsQuery {
+    name: &'static str,
+    record_type: u16,
+    class: u16,
+    resolver: SocketAddr,
+}
+
+impl DnsSourceConfig {
+    fn query(&self) -> DnsQuery {
+        let (name, record_type, class, v4, v6): (_, _, _, Ipv4Addr, Ipv6Addr) = match self.service {
+            DnsService::Opendns => (
+                "myip.opendns.com",
+                if self.ipv6 { TYPE_AAAA } else { TYPE_A },
+                CLASS_IN,
+                Ipv4Addr::new(208, 67, 222, 222),
+                Ipv6Addr::new(0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35),
+            ),
+            DnsService::Google => (
+                "o-o.myaddr.l.google.com",
+                TYPE_TXT,
+                CLASS_IN,
+                Ipv4Addr::new(216, 239, 32, 10),
+                Ipv6Addr::new(0x2001, 0x4860, 0x4802, 0x32, 0, 0, 0, 0xa),
+            ),
+            DnsService::Cloudflare => (
+                "whoami.cloudflare",
+                TYPE_TXT,
+                CLASS_CH,
+                Ipv4Addr::new(1, 1, 1, 1),
+                Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111),
+            ),
+        };
+        let resolver = self.resolver.unwrap_or(if self.ipv6 {
+            SocketAddr::new(IpAddr::V6(v6), 53)
+        } else {
+            SocketAddr::new(IpAddr::V4(v4), 53)
+        });
+        DnsQuery {
+            name,
+            record_type,
+            class,
+            resolver,
+        }
+    }
+}
+
+// ========== DNS 查询实现 ==========
+
+/// 通过DNS查询获取当前IP，只返回与ipv6配置一致的地址
+pub(super) fn dns_ip(config: &DnsSourceConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let query = config.query();
+    let id = u16::from_be_bytes(rand_bytes());
+    let packet = encode_query(id, &query)?;
+
+    let socket = bind.udp_socket(query.resolver.is_ipv6())?;
+    socket.connect(query.resolver)?;
+    socket.set_read_timeout(Some(DNS_TIMEOUT))?;
+    socket.send(&packet)?;
+
+    let mut buf = [0u8; 1500];
+    let len = socket
+        .recv(&mut buf)
+        .map_err(|e| anyhow!("no DNS response from {}: {}", query.resolver, e))?;
+    let answers = decode_answers(&buf[..len], id, &query)?;
+    debug!(
+        "DNS answers for {} from {}: {:?}",
+        query.name, query.resolver, answers
+    );
+
+    answers
+        .iter()
+        .filter_map(|answer| answer.trim().parse::<IpAddr>().ok())
+        .find(|ip| ip.is_ipv6() == config.ipv6)
+        .ok_or_else(|| {
+            anyhow!(
+                "no {} address in DNS answers for {}: {:?}",
+                if config.ipv6 { "IPv6" } else { "IPv4" },
+                query.name,
+                answers
+            )
+        })
+}
+
+/// 编码查询报文
+fn encode_query(id: u16, query: &DnsQuery) -> Result<Vec<u8>, Error> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend_from_slice(&id.to_be_bytes());
+    // RD=1，一个问题
+    packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in query.name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(anyhow!("invalid DNS name: {}", query.name));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&query.record_type.to_be_bytes());
+    packet.extend_from_slice(&query.class.to_be_bytes());
+    Ok(packet)
+}
+
+/// 解析响应中与查询类型一致的回答，A/AAAA 返回地址文本，TXT 返回其中的每个字符串
+fn decode_answers(packet: &[u8], id: u16, query: &DnsQuery) -> Result<Vec<String>, Error> {
+    let invalid = || anyhow!("invalid DNS response from {}", query.resolver);
+    let read_u16 = |pos: usize| -> Result<u16, Error> {
+        let bytes = packet.get(pos..pos + 2).ok_or_else(invalid)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    // 先确认报文头完整，再读取其中的字段
+    if packet.len() < HEADER_LEN {
+        return Err(invalid());
+    }
+    if read_u16(0)? != id || packet[2] & 0x80 == 0 {
+        return Err(invalid());
+    }
+    let rcode = packet[3] & 0x0f;
+    if rcode != 0 {
+        return Err(anyhow!(
+            "DNS query for {} failed with rcode {}",
+            query.name,
+            rcode
+        ));
+    }
+    let questions = read_u16(4)?;
+    let answers = read_u16(6)?;
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        pos = skip_name(packet, pos).ok_or_else(invalid)? + 4;
+    }
+
+    let mut results = Vec::new();
+    for _ in 0..answers {
+        pos = skip_name(packet, pos).ok_or_else(invalid)?;
+        let record_type = read_u16(pos)?;
+        let rdlength = read_u16(pos + 8)? as usize;
+        let rdata = packet
+            .get(pos + 10..pos + 10 + rdlength)
+            .ok_or_else(invalid)?;
+        pos += 10 + rdlength;
+
+        if record_type != query.record_type {
+            continue;
+        }
+        match record_type {
+            TYPE_A => {
+                let octets: [u8; 4] = rdata.try_into()?;
+                results.push(Ipv4Addr::from(octets).to_string());
+            }
+            TYPE_AAAA => {
+                let octets: [u8; 16] = rdata.try_into()?;
+                results.push(Ipv6Addr::from(octets).to_string());
+            }
+            _ => {
+                // TXT: 若干个以长度开头的字符串
+                let mut rest = rdata;
+                while let Some((&len, tail)) = rest.split_first() {
+                    let text = tail.get(..len as usize).ok_or_else(invalid)?;
+                    results.push(String::from_utf8_lossy(text).into_owned());
+                    rest = &tail[len as usize..];
+                }
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// 跳过报文中的域名（支持压缩指针），返回域名之后的位置
+fn skip_name(packet: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *packet.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // 压缩指针占两个字节，之后域名结束
+            len if len & 0xc0 == 0xc0 => return Some(pos + 2),
+            len => pos += 1 + len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    /// 构造只带一个回答的响应，回答的名称使用指向问题的压缩指针
+    fn response(request: &[u8], record_type: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut packet = request[..2].to_vec();
+        packet.extend_from_slice(&[0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+        packet.extend_from_slice(&request[12..]);
+        packet.extend_from_slice(&[0xc0, 12]);
+        packet.extend_from_slice(&record_type.to_be_bytes());
+        packet.extend_from_slice(&request[request.len() - 2..]);
+        packet.extend_from_slice(&60u32.to_be_bytes());
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(rdata);
+        packet
+    }
+
+    /// 编码TXT记录的rdata
+    fn txt(strings: &[&str]) -> Vec<u8> {
+        let mut rdata = Vec::new();
+        for text in strings {
+            rdata.push(text.len() as u8);
+            rdata.extend_from_slice(text.as_bytes());
+        }
+        rdata
+    }
+
+    #[test]
+    fn test_dns_ip() {
+        // 本地模拟DNS服务器：OpenDNS返回A记录，Google返回带ECS信息的TXT记录，Cloudflare返回CH类TXT记录
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = server.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..3 {
+                let (len, peer) = server.recv_from(&mut buf).unwrap();
+                let request = &buf[..len];
+                let class = u16::from_be_bytes([request[len - 2], request[len - 1]]);
+                let record_type = u16::from_be_bytes([request[len - 4], request[len - 3]]);
+                let rdata: Vec<u8> = match (record_type, class) {
+                    (TYPE_A, CLASS_IN) => vec![192, 0, 2, 1],
+                    (TYPE_TXT, CLASS_IN) => txt(&["edns0-client-subnet 0", "192.0.2.200"]),
+                    (TYPE_TXT, CLASS_CH) => txt(&["192.0.2.100"]),
+                    _ => unreachable!(),
+                };
+                server
+                    .send_to(&response(request, record_type, &rdata), peer)
+                    .unwrap();
+            }
+        });
+
+        let config = |service| DnsSourceConfig {
+            service,
+            ipv6: false,
+            resolver: Some(resolver),
+        };
+        assert_eq!(
+            dns_ip(&config(DnsService::Opendns), &Bind::default())
+                .unwrap()
+                .to_string(),
+            "192.0.2.1"
+        );
+        assert_eq!(
+            dns_ip(&config(DnsService::Google), &Bind::default())
+                .unwrap()
+                .to_string(),
+            "192.0.2.200"
+        );
+        assert_eq!(
+            dns_ip(&config(DnsService::Cloudflare), &Bind::default())
+                .unwrap()
+                .to_string(),
+            "192.0.2.100"
+        );
+        handle.join().unwrap();
+
+        // 服务默认的DNS服务器按IP类型区分
+        let mut config = DnsSourceConfig::default();
+        assert_eq!(config.query().resolver.to_string(), "208.67.222.222:53");
+        config.ipv6 = true;
+        assert_eq!(config.query().record_type, TYPE_AAAA);
+        assert_eq!(config.query().resolver.to_string(), "[2620:119:35::35]:53");
+    }
+
+    #[test]
+    fn test_truncated_response() {
+        // 不足报文头长度的响应返回错误而不是panic
+        let query = DnsSourceConfig::default().query();
+        for len in [0, 2, 3, HEADER_LEN - 1] {
+            let mut packet = vec![0u8; len];
+            if len >= 2 {
+                packet[..2].copy_from_slice(&0x1234u16.to_be_bytes());
+            }
+            if len >= 3 {
+                packet[2] = 0x80;
+            }
+            assert!(decode_answers(&packet, 0x1234, &query).is_err());
+        }
+    }
+}
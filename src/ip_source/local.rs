@@ -0,0 +1,103 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info};
+use std::fs;
+use std::net::IpAddr;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use super::parse_ip;
+
+// ========== 外部命令 / 文件 ==========
+
+/// 等待命令执行完成的超时时间，超时后结束进程
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 执行命令，标准输出即为当前IP
+pub(super) fn command_ip(command: &str) -> Result<IpAddr, Error> {
+    debug!("Executing ip source command: {command}");
+
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("powershell");
+        cmd.creation_flags(0x08000000)
+            .arg("-ExecutionPolicy")
+            .arg("Bypass")
+            .arg("-Command")
+            .arg(command);
+        cmd
+    };
+
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    let output = crate::process::output_with_timeout(&mut cmd, None, COMMAND_TIMEOUT)
+        .map_err(|e| anyhow!("Failed to execute ip source command: {}", e))?
+        .ok_or_else(|| {
+            anyhow!(
+                "ip source command timed out after {}s",
+                COMMAND_TIMEOUT.as_secs()
+            )
+        })?;
+    let status = output.status;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        info!("ip source command stderr: {}", stderr.trim());
+    }
+    if !status.success() {
+        return Err(anyhow!(
+            "ip source command failed with exit code {:?}: {}",
+            status.code(),
+            stderr.trim()
+        ));
+    }
+    parse_ip(&stdout)
+}
+
+/// 读取文件，文件内容即为当前IP
+pub(super) fn file_ip(path: &Path) -> Result<IpAddr, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    parse_ip(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_command_and_file_ip() {
+        let path = env::temp_dir().join(format!("ddns_wan_ip_{}", std::process::id()));
+        fs::write(&path, "203.0.113.5\n").unwrap();
+        assert_eq!(file_ip(&path).unwrap().to_string(), "203.0.113.5");
+        fs::write(&path, "not an ip\n").unwrap();
+        assert!(file_ip(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(file_ip(&path).is_err());
+
+        #[cfg(not(windows))]
+        {
+            assert_eq!(
+                command_ip("echo '  2001:db8::5  '").unwrap().to_string(),
+                "2001:db8::5"
+            );
+            assert!(command_ip("echo 192.0.2.1; exit 3").is_err());
+            assert!(command_ip("echo 'WAN: 192.0.2.1'").is_err());
+            // 输出超过管道缓冲区时不会阻塞到超时
+            assert_eq!(
+                command_ip("head -c 200000 /dev/zero | tr '\\0' ' '; echo 192.0.2.1")
+                    .unwrap()
+                    .to_string(),
+                "192.0.2.1"
+            );
+        }
+    }
+}
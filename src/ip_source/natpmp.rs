@@ -0,0 +1,243 @@
+use anyhow::{Error, anyhow};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use super::{GATEWAY_TIMEOUT, rand_bytes};
+use crate::bind::Bind;
+
+// ========== NAT-PMP / PCP 相关结构 ==========
+
+/// NAT-PMP 和 PCP 共用的配置
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NatPmpConfig {
+    /// 网关地址，未设置时使用默认路由的网关 (仅Linux，设置了bind_interface时使用该网卡上默认路由的网关)
+    pub gateway: Option<IpAddr>,
+    /// 网关端口，默认为5351
+    pub port: u16,
+}
+
+impl Default for NatPmpConfig {
+    fn default() -> Self {
+        NatPmpConfig {
+            gateway: None,
+            port: 5351,
+        }
+    }
+}
+
+impl NatPmpConfig {
+    fn gateway_addr(&self, bind: &Bind) -> Result<SocketAddr, Error> {
+        let gateway = match self.gateway {
+            Some(gateway) => gateway,
+            None => IpAddr::V4(default_gateway(bind.interface.as_deref())?),
+        };
+        Ok(SocketAddr::new(gateway, self.port))
+    }
+}
+
+/// 从 /proc/net/route 读取默认路由的网关，指定网卡时只使用该网卡上的默认路由
+fn default_gateway(interface: Option<&str>) -> Result<Ipv4Addr, Error> {
+    let routes = fs::read_to_string("/proc/net/route").map_err(|e| {
+        anyhow!(
+            "failed to detect default gateway, please set gateway: {}",
+            e
+        )
+    })?;
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Iface Destination Gateway ...，地址为小端序的十六进制
+            if fields.len() < 3
+                || fields[1] != "00000000"
+                || interface.is_some_and(|interface| fields[0] != interface)
+            {
+                return None;
+            }
+            let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+            Some(Ipv4Addr::from(gateway.swap_bytes()))
+        })
+        .find(|gateway| !gateway.is_unspecified())
+        .ok_or_else(|| anyhow!("no default gateway found, please set gateway"))
+}
+
+/// 创建连接到网关的UDP socket
+fn connect(gateway: SocketAddr, bind: &Bind) -> Result<UdpSocket, Error> {
+    let socket = bind.udp_socket(gateway.is_ipv6())?;
+    socket.connect(gateway)?;
+    socket.set_read_timeout(Some(GATEWAY_TIMEOUT))?;
+    Ok(socket)
+}
+
+/// 向网关发送请求并等待响应
+fn exchange(socket: &UdpSocket, packet: &[u8]) -> Result<Vec<u8>, Error> {
+    let gateway = socket.peer_addr()?;
+    socket.send(packet)?;
+    let mut buf = [0u8; 1100];
+    let len = socket
+        .recv(&mut buf)
+        .map_err(|e| anyhow!("no response from gateway {}: {}", gateway, e))?;
+    Ok(buf[..len].to_vec())
+}
+
+// ========== NAT-PMP (RFC 6886) ==========
+
+/// 通过NAT-PMP的外部地址请求 (opcode 0) 查询网关的WAN IPv4地址
+pub(super) fn natpmp_external_ip(config: &NatPmpConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let socket = connect(config.gateway_addr(bind)?, bind)?;
+    let response = exchange(&socket, &[0, 0])?;
+    parse_natpmp_response(&response)
+}
+
+/// 解析外部地址响应: version(1) opcode(1) result(2) epoch(4) address(4)
+fn parse_natpmp_response(response: &[u8]) -> Result<IpAddr, Error> {
+    if response.len() < 12 || response[0] != 0 || response[1] != 128 {
+        return Err(anyhow!("invalid NAT-PMP response: {:?}", response));
+    }
+    let result = u16::from_be_bytes([response[2], response[3]]);
+    if result != 0 {
+        return Err(anyhow!(
+            "NAT-PMP request failed with result code {}",
+            result
+        ));
+    }
+    let ip = Ipv4Addr::new(response[8], response[9], response[10], response[11]);
+    debug!("NAT-PMP external address: {ip}");
+    check_external(IpAddr::V4(ip))
+}
+
+// ========== PCP (RFC 6887) ==========
+
+const PCP_VERSION: u8 = 2;
+const PCP_OPCODE_MAP: u8 = 1;
+const PCP_PROTOCOL_UDP: u8 = 17;
+
+/// PCP没有单独查询外部地址的请求，这里为本地UDP端口申请一个短期映射，
+/// 从响应中取得分配的外部地址后立即删除该映射
+pub(super) fn pcp_external_ip(config: &NatPmpConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let socket = connect(config.gateway_addr(bind)?, bind)?;
+    // 映射的内部地址即本地socket的地址和端口
+    let local = socket.local_addr()?;
+    let nonce: [u8; 12] = rand_bytes();
+    let response = exchange(&socket, &pcp_map_request(local, 60, &nonce))?;
+    let ip = parse_pcp_response(&response, &nonce)?;
+
+    // 删除映射，失败不影响结果
+    if let Err(e) = socket.send(&pcp_map_request(local, 0, &nonce)) {
+        debug!("failed to delete PCP mapping: {e}");
+    }
+    check_external(ip)
+}
+
+/// MAP请求: 公共头(24) + MAP数据(36)
+fn pcp_map_request(local: SocketAddr, lifetime: u32, nonce: &[u8; 12]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(60);
+    packet.extend_from_slice(&[PCP_VERSION, PCP_OPCODE_MAP, 0, 0]);
+    packet.extend_from_slice(&lifetime.to_be_bytes());
+    packet.extend_from_slice(&ipv6_octets(local.ip()));
+    packet.extend_from_slice(nonce);
+    packet.extend_from_slice(&[PCP_PROTOCOL_UDP, 0, 0, 0]);
+    packet.extend_from_slice(&local.port().to_be_bytes());
+    // 建议的外部端口和地址均不指定
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&[0u8; 16]);
+    packet
+}
+
+/// 解析MAP响应，返回分配的外部地址
+fn parse_pcp_response(response: &[u8], nonce: &[u8; 12]) -> Result<IpAddr, Error> {
+    if response.len() < 60 || response[0] != PCP_VERSION || response[1] != 0x80 | PCP_OPCODE_MAP {
+        return Err(anyhow!("invalid PCP response: {:?}", response));
+    }
+    let result = response[3];
+    if result != 0 {
+        return Err(anyhow!("PCP request failed with result code {}", result));
+    }
+    if &response[24..36] != nonce {
+        return Err(anyhow!("PCP response nonce mismatch"));
+    }
+    let octets: [u8; 16] = response[44..60].try_into()?;
+    let ip = Ipv6Addr::from(octets);
+    debug!("PCP assigned external address: {ip}");
+    Ok(ip
+        .to_ipv4_mapped()
+        .map(IpAddr::V4)
+        .unwrap_or(IpAddr::V6(ip)))
+}
+
+/// PCP中IPv4地址以IPv4映射的IPv6地址 (::ffff:a.b.c.d) 表示
+fn ipv6_octets(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+/// 网关返回未指定地址时表示WAN口尚未连接
+fn check_external(ip: IpAddr) -> Result<IpAddr, Error> {
+    if ip.is_unspecified() {
+        Err(anyhow!("gateway has no external address"))
+    } else {
+        Ok(ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_natpmp_and_pcp() {
+        // 本地模拟网关：NAT-PMP返回203.0.113.7，PCP原样带回nonce并分配203.0.113.8
+        let responder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = responder.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 1100];
+            for _ in 0..3 {
+                let (len, peer) = responder.recv_from(&mut buf).unwrap();
+                let request = &buf[..len];
+                let response = if request == [0, 0] {
+                    vec![0, 128, 0, 0, 0, 0, 0, 1, 203, 0, 113, 7]
+                } else if request[4..8] == 0u32.to_be_bytes() {
+                    // 删除映射的请求，无需响应
+                    continue;
+                } else {
+                    let mut response = vec![PCP_VERSION, 0x80 | PCP_OPCODE_MAP, 0, 0];
+                    response.extend_from_slice(&request[4..8]);
+                    response.extend_from_slice(&[0u8; 16]);
+                    response.extend_from_slice(&request[24..44]);
+                    let external: Ipv4Addr = "203.0.113.8".parse().unwrap();
+                    response.extend_from_slice(&external.to_ipv6_mapped().octets());
+                    response
+                };
+                responder.send_to(&response, peer).unwrap();
+            }
+        });
+
+        let config = NatPmpConfig {
+            gateway: Some("127.0.0.1".parse().unwrap()),
+            port,
+        };
+        assert_eq!(
+            natpmp_external_ip(&config, &Bind::default())
+                .unwrap()
+                .to_string(),
+            "203.0.113.7"
+        );
+        assert_eq!(
+            pcp_external_ip(&config, &Bind::default())
+                .unwrap()
+                .to_string(),
+            "203.0.113.8"
+        );
+        handle.join().unwrap();
+
+        assert!(parse_natpmp_response(&[0, 128, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0]).is_err());
+        assert!(parse_natpmp_response(&[0, 128, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]).is_err());
+    }
+}
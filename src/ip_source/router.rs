@@ -0,0 +1,280 @@
+use anyhow::{Error, anyhow};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::net::IpAddr;
+
+use super::{GATEWAY_TIMEOUT, is_global_ipv6, parse_ip};
+use crate::bind::Bind;
+
+// ========== OpenWrt ubus 相关结构 ==========
+
+/// 未登录时使用的ubus会话
+const UBUS_NULL_SESSION: &str = "00000000000000000000000000000000";
+
+/// OpenWrt ubus JSON-RPC 配置
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UbusConfig {
+    /// ubus JSON-RPC 地址，如 "http://192.168.1.1/ubus"
+    pub url: String,
+    /// 登录用户名，默认为 "root"
+    #[serde(default = "default_ubus_username")]
+    pub username: String,
+    /// 登录密码
+    pub password: String,
+    /// 逻辑接口名称，默认IPv4为 "wan"，IPv6为 "wan6"
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// 是否获取IPv6地址
+    #[serde(default)]
+    pub ipv6: bool,
+}
+
+fn default_ubus_username() -> String {
+    "root".to_string()
+}
+
+// ========== MikroTik RouterOS 相关结构 ==========
+
+/// MikroTik RouterOS REST API 配置 (RouterOS v7)
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MikrotikConfig {
+    /// 路由器地址，如 "https://192.168.88.1"
+    pub url: String,
+    /// REST API 用户名
+    pub username: String,
+    /// REST API 密码
+    pub password: String,
+    /// WAN口的接口名称，如 "pppoe-out1"
+    pub interface: String,
+    /// 是否获取IPv6地址
+    #[serde(default)]
+    pub ipv6: bool,
+    /// 是否接受自签名证书，路由器默认证书通常为自签名
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+/// /ip/address 和 /ipv6/address 返回的地址条目，RouterOS以字符串表示布尔值
+#[derive(Deserialize, Debug)]
+struct MikrotikAddress {
+    address: String,
+    interface: String,
+    #[serde(default)]
+    disabled: String,
+    #[serde(default)]
+    invalid: String,
+}
+
+fn http_client(
+    bind: &Bind,
+    accept_invalid_certs: bool,
+) -> Result<reqwest::blocking::Client, Error> {
+    let builder = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .timeout(GATEWAY_TIMEOUT)
+        .danger_accept_invalid_certs(accept_invalid_certs);
+    Ok(bind.client_builder(builder).build()?)
+}
+
+// ========== OpenWrt ubus 实现 ==========
+
+/// 登录ubus后调用 network.interface.<interface> status，返回接口上的地址
+pub(super) fn ubus_ip(config: &UbusConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let client = http_client(bind, false)?;
+    let login = ubus_call(
+        &client,
+        &config.url,
+        UBUS_NULL_SESSION,
+        "session",
+        "login",
+        json!({ "username": config.username, "password": config.password }),
+    )?;
+    let session = login["ubus_rpc_session"]
+        .as_str()
+        .ok_or_else(|| anyhow!("invalid ubus login response: {login}"))?;
+
+    let interface = config
+        .interface
+        .as_deref()
+        .unwrap_or(if config.ipv6 { "wan6" } else { "wan" });
+    let status = ubus_call(
+        &client,
+        &config.url,
+        session,
+        &format!("network.interface.{interface}"),
+        "status",
+        json!({}),
+    )?;
+    debug!("ubus network.interface.{interface} status: {status}");
+
+    let key = if config.ipv6 {
+        "ipv6-address"
+    } else {
+        "ipv4-address"
+    };
+    status[key]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry["address"].as_str())
+        .filter_map(|address| parse_ip(address).ok())
+        .find(|ip| match ip {
+            IpAddr::V4(_) => !config.ipv6,
+            IpAddr::V6(ip) => config.ipv6 && is_global_ipv6(ip),
+        })
+        .ok_or_else(|| anyhow!("no {key} on ubus interface {interface}"))
+}
+
+/// 发起一次ubus调用，返回结果中的数据；ubus状态码非0时返回错误
+fn ubus_call(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    session: &str,
+    object: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, Error> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "call",
+        "params": [session, object, method, params],
+    });
+    let response: Value = client
+        .post(url)
+        .json(&request)
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.json())
+        .map_err(|e| anyhow!("ubus call {object} {method} failed: {e}"))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(anyhow!("ubus call {object} {method} failed: {error}"));
+    }
+    // result 为 [状态码, 数据]，状态码 6 表示权限不足
+    let code = response["result"][0].as_i64();
+    match code {
+        Some(0) => Ok(response["result"][1].clone()),
+        Some(6) => Err(anyhow!(
+            "ubus call {object} {method} permission denied, check the rpcd acl for this user"
+        )),
+        _ => Err(anyhow!(
+            "ubus call {object} {method} failed: {}",
+            response["result"]
+        )),
+    }
+}
+
+// ========== MikroTik RouterOS 实现 ==========
+
+/// 查询 /rest/ip/address（IPv6为 /rest/ipv6/address），返回WAN接口上启用的地址
+pub(super) fn mikrotik_ip(config: &MikrotikConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let client = http_client(bind, config.accept_invalid_certs)?;
+    let path = if config.ipv6 {
+        "ipv6/address"
+    } else {
+        "ip/address"
+    };
+    let url = format!("{}/rest/{}", config.url.trim_end_matches('/'), path);
+    let addresses: Vec<MikrotikAddress> = client
+        .get(&url)
+        .basic_auth(&config.username, Some(&config.password))
+        .query(&[("interface", config.interface.as_str())])
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.json())
+        .map_err(|e| anyhow!("RouterOS request {url} failed: {e}"))?;
+    debug!("RouterOS {path}: {addresses:?}");
+
+    addresses
+        .iter()
+        .filter(|entry| {
+            entry.interface == config.interface
+                && entry.disabled != "true"
+                && entry.invalid != "true"
+        })
+        // 地址带有前缀长度，如 "203.0.113.7/32"
+        .filter_map(|entry| parse_ip(entry.address.split('/').next()?).ok())
+        .find(|ip| match ip {
+            IpAddr::V4(_) => !config.ipv6,
+            IpAddr::V6(ip) => config.ipv6 && is_global_ipv6(ip),
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "no enabled {} address on RouterOS interface {}",
+                if config.ipv6 { "IPv6" } else { "IPv4" },
+                config.interface
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_router_ip() {
+        // 本地模拟OpenWrt：先登录，再返回wan接口状态
+        let (address, handle) = serve_http(vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":[0,{"ubus_rpc_session":"c1ed6c7b025d0caca723a816fa61b668"}]}"#,
+            r#"{"jsonrpc":"2.0","id":1,"result":[0,{"up":true,"ipv4-address":[{"address":"100.64.3.9","mask":32}]}]}"#,
+        ]);
+        let config = UbusConfig {
+            url: format!("http://{address}/ubus"),
+            username: "root".to_string(),
+            password: "secret".to_string(),
+            interface: None,
+            ipv6: false,
+        };
+        assert_eq!(
+            ubus_ip(&config, &Bind::default()).unwrap().to_string(),
+            "100.64.3.9"
+        );
+        let requests = handle.join().unwrap();
+        let call: Value = serde_json::from_str(&requests[1].1).unwrap();
+        assert_eq!(
+            call["params"],
+            json!([
+                "c1ed6c7b025d0caca723a816fa61b668",
+                "network.interface.wan",
+                "status",
+                {}
+            ])
+        );
+
+        // 本地模拟RouterOS：跳过禁用的地址和其他接口的地址
+        let (address, handle) = serve_http(vec![
+            r#"[{".id":"*1","address":"192.168.88.1/24","interface":"bridge","disabled":"false","invalid":"false"},
+                {".id":"*2","address":"198.51.100.1/32","interface":"pppoe-out1","disabled":"true","invalid":"false"},
+                {".id":"*3","address":"198.51.100.2/32","interface":"pppoe-out1","disabled":"false","invalid":"false"}]"#,
+        ]);
+        let config = MikrotikConfig {
+            url: format!("http://{address}/"),
+            username: "ddns".to_string(),
+            password: "secret".to_string(),
+            interface: "pppoe-out1".to_string(),
+            ipv6: false,
+            accept_invalid_certs: false,
+        };
+        assert_eq!(
+            mikrotik_ip(&config, &Bind::default()).unwrap().to_string(),
+            "198.51.100.2"
+        );
+        let requests = handle.join().unwrap();
+        assert!(
+            requests[0]
+                .0
+                .starts_with("GET /rest/ip/address?interface=pppoe-out1 ")
+        );
+        assert!(
+            requests[0]
+                .0
+                .to_lowercase()
+                .contains("authorization: basic ")
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::{Error, anyhow};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use super::rand_bytes;
+use crate::bind::Bind;
+
+// ========== STUN (RFC 5389) 相关结构 ==========
+
+/// 等待STUN响应的超时时间
+const STUN_TIMEOUT: Duration = Duration::from_secs(3);
+
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// STUN 配置
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StunConfig {
+    /// STUN服务器列表 (如 "stun.l.google.com:19302")，按顺序尝试直到成功
+    pub servers: Vec<String>,
+    /// 是否获取IPv6地址，为true时只使用服务器的IPv6地址
+    pub ipv6: bool,
+}
+
+impl Default for StunConfig {
+    fn default() -> Self {
+        StunConfig {
+            servers: vec![
+                "stun.cloudflare.com:3478".to_string(),
+                "stun.l.google.com:19302".to_string(),
+            ],
+            ipv6: false,
+        }
+    }
+}
+
+// ========== STUN 实现 ==========
+
+/// 依次向STUN服务器发送Binding请求，返回第一个成功响应中的映射地址
+pub(super) fn stun_ip(config: &StunConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let mut errors = Vec::new();
+    for server in &config.servers {
+        match query_server(server, config.ipv6, bind) {
+            Ok(ip) => return Ok(ip),
+            Err(e) => {
+                warn!("STUN server {server} failed: {e}");
+                errors.push(format!("{server}: {e}"));
+            }
+        }
+    }
+    Err(anyhow!("all STUN servers failed: {}", errors.join("; ")))
+}
+
+/// 向单个STUN服务器查询，只使用与ipv6配置一致的服务器地址
+fn query_server(server: &str, ipv6: bool, bind: &Bind) -> Result<IpAddr, Error> {
+    let address = server
+        .to_socket_addrs()
+        .map_err(|e| anyhow!("failed to resolve {server}: {e}"))?
+        .find(|address| address.is_ipv6() == ipv6)
+        .ok_or_else(|| {
+            anyhow!(
+                "{server} has no {} address",
+                if ipv6 { "IPv6" } else { "IPv4" }
+            )
+        })?;
+
+    let socket = bind.udp_socket(ipv6)?;
+    socket.connect(address)?;
+    socket.set_read_timeout(Some(STUN_TIMEOUT))?;
+
+    let transaction_id: [u8; 12] = rand_bytes();
+    socket.send(&binding_request(&transaction_id))?;
+    let mut buf = [0u8; 1500];
+    let len = socket
+        .recv(&mut buf)
+        .map_err(|e| anyhow!("no response from {address}: {e}"))?;
+    let mapped = parse_binding_response(&buf[..len], &transaction_id)?;
+    debug!("STUN server {server} ({address}) mapped address: {mapped}");
+    Ok(mapped.ip())
+}
+
+/// Binding请求：类型、长度0、magic cookie、transaction id，不带属性
+fn binding_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(20);
+    packet.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    packet.extend_from_slice(transaction_id);
+    packet
+}
+
+/// 解析Binding成功响应，优先使用XOR-MAPPED-ADDRESS，其次是MAPPED-ADDRESS
+fn parse_binding_response(packet: &[u8], transaction_id: &[u8; 12]) -> Result<SocketAddr, Error> {
+    if packet.len() < 20
+        || packet[4..8] != MAGIC_COOKIE.to_be_bytes()
+        || &packet[8..20] != transaction_id
+    {
+        return Err(anyhow!("invalid STUN response"));
+    }
+    let message_type = u16::from_be_bytes([packet[0], packet[1]]);
+    if message_type != BINDING_SUCCESS {
+        return Err(anyhow!(
+            "STUN binding failed, message type {message_type:#06x}"
+        ));
+    }
+    let length = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    let attributes = packet
+        .get(20..20 + length)
+        .ok_or_else(|| anyhow!("truncated STUN response"))?;
+
+    let mut mapped = None;
+    let mut rest = attributes;
+    while rest.len() >= 4 {
+        let attr_type = u16::from_be_bytes([rest[0], rest[1]]);
+        let attr_len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let value = rest
+            .get(4..4 + attr_len)
+            .ok_or_else(|| anyhow!("truncated STUN attribute"))?;
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => {
+                return parse_address(value, Some(transaction_id));
+            }
+            ATTR_MAPPED_ADDRESS => mapped = Some(parse_address(value, None)?),
+            _ => {}
+        }
+        // 属性值按4字节对齐
+        let padded = (4 + attr_len).div_ceil(4) * 4;
+        rest = rest.get(padded..).unwrap_or_default();
+    }
+    mapped.ok_or_else(|| anyhow!("no mapped address in STUN response"))
+}
+
+/// 解析地址属性：reserved(1) family(1) port(2) address(4/16)，
+/// XOR-MAPPED-ADDRESS 的端口和地址与magic cookie（IPv6时还有transaction id）异或
+fn parse_address(value: &[u8], xor: Option<&[u8; 12]>) -> Result<SocketAddr, Error> {
+    let invalid = || anyhow!("invalid STUN address attribute");
+    let family = *value.get(1).ok_or_else(invalid)?;
+    let mut port = u16::from_be_bytes(value.get(2..4).ok_or_else(invalid)?.try_into()?);
+    let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+    if let Some(transaction_id) = xor {
+        port ^= (MAGIC_COOKIE >> 16) as u16;
+        key.extend_from_slice(transaction_id);
+    }
+    let decode = |address: &[u8]| -> Vec<u8> {
+        match xor {
+            Some(_) => address.iter().zip(&key).map(|(a, k)| a ^ k).collect(),
+            None => address.to_vec(),
+        }
+    };
+
+    let ip = match family {
+        0x01 => {
+            let octets: [u8; 4] = decode(value.get(4..8).ok_or_else(invalid)?)
+                .try_into()
+                .map_err(|_| invalid())?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        0x02 => {
+            let octets: [u8; 16] = decode(value.get(4..20).ok_or_else(invalid)?)
+                .try_into()
+                .map_err(|_| invalid())?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return Err(invalid()),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    #[test]
+    fn test_stun_ip() {
+        // 本地模拟STUN服务器：返回带XOR-MAPPED-ADDRESS的Binding成功响应
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            let (len, peer) = server.recv_from(&mut buf).unwrap();
+            assert_eq!(len, 20);
+            let mapped: Ipv4Addr = "198.51.100.9".parse().unwrap();
+            let xor_ip: Vec<u8> = mapped
+                .octets()
+                .iter()
+                .zip(MAGIC_COOKIE.to_be_bytes())
+                .map(|(a, k)| a ^ k)
+                .collect();
+            let mut response = BINDING_SUCCESS.to_be_bytes().to_vec();
+            response.extend_from_slice(&12u16.to_be_bytes());
+            response.extend_from_slice(&buf[4..20]);
+            response.extend_from_slice(&ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+            response.extend_from_slice(&8u16.to_be_bytes());
+            response.extend_from_slice(&[0, 0x01]);
+            response.extend_from_slice(&(54321 ^ (MAGIC_COOKIE >> 16) as u16).to_be_bytes());
+            response.extend_from_slice(&xor_ip);
+            server.send_to(&response, peer).unwrap();
+        });
+
+        // 第一个服务器无法解析，回退到第二个
+        let config = StunConfig {
+            servers: vec!["invalid host name:3478".to_string(), address.to_string()],
+            ipv6: false,
+        };
+        assert_eq!(
+            stun_ip(&config, &Bind::default()).unwrap().to_string(),
+            "198.51.100.9"
+        );
+        handle.join().unwrap();
+
+        // IPv6的XOR-MAPPED-ADDRESS同时与transaction id异或
+        let transaction_id: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        let ip: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let mut key = MAGIC_COOKIE.to_be_bytes().to_vec();
+        key.extend_from_slice(&transaction_id);
+        let mut value = vec![0, 0x02, 0, 0];
+        value.extend(ip.octets().iter().zip(&key).map(|(a, k)| a ^ k));
+        assert_eq!(
+            parse_address(&value, Some(&transaction_id)).unwrap().ip(),
+            IpAddr::V6(ip)
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::{Error, anyhow};
+use log::debug;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+
+use super::{GATEWAY_TIMEOUT, http_client, parse_ip};
+use crate::bind::Bind;
+
+// ========== UPnP IGD 相关结构 ==========
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+
+/// UPnP IGD 配置
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpnpConfig {
+    /// 网关的设备描述地址 (如 "http://192.168.1.1:1900/rootDesc.xml")，设置后跳过SSDP发现
+    pub location: Option<String>,
+    /// SSDP发现请求的目标地址，默认为组播地址 239.255.255.250:1900
+    pub ssdp_address: Option<SocketAddr>,
+}
+
+/// 设备描述中的WAN连接服务
+#[derive(Debug)]
+struct WanService {
+    service_type: String,
+    control_url: String,
+}
+
+// ========== UPnP IGD 实现 ==========
+
+/// 通过SOAP调用 GetExternalIPAddress 查询网关的WAN地址
+pub(super) fn external_ip(config: &UpnpConfig, bind: &Bind) -> Result<IpAddr, Error> {
+    let location = match &config.location {
+        Some(location) => location.clone(),
+        None => discover(config.ssdp_address, bind)?,
+    };
+    debug!("UPnP gateway description: {location}");
+
+    let client = http_client(bind)?;
+    let description = client
+        .get(&location)
+        .timeout(GATEWAY_TIMEOUT)
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.text())
+        .map_err(|e| anyhow!("failed to fetch UPnP description {location}: {e}"))?;
+    let (url_base, service) = parse_description(&description)?;
+    let base = Url::parse(url_base.as_deref().unwrap_or(&location))?;
+    let control_url = base.join(&service.control_url)?;
+    debug!(
+        "UPnP WAN service {} at {}",
+        service.service_type, control_url
+    );
+
+    let body = format!(
+        r#"<?xml version="1.0"?><s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/"><s:Body><u:GetExternalIPAddress xmlns:u="{}"></u:GetExternalIPAddress></s:Body></s:Envelope>"#,
+        service.service_type
+    );
+    let response = client
+        .post(control_url)
+        .timeout(GATEWAY_TIMEOUT)
+        .header("Content-Type", r#"text/xml; charset="utf-8""#)
+        .header(
+            "SOAPAction",
+            format!("\"{}#GetExternalIPAddress\"", service.service_type),
+        )
+        .body(body)
+        .send()
+        .and_then(|res| res.error_for_status())
+        .and_then(|res| res.text())
+        .map_err(|e| anyhow!("UPnP GetExternalIPAddress failed: {e}"))?;
+
+    let ip = element_text(&response, "NewExternalIPAddress")?
+        .ok_or_else(|| anyhow!("invalid UPnP response: {response}"))?;
+    let ip = parse_ip(&ip)?;
+    if ip.is_unspecified() {
+        return Err(anyhow!("gateway has no external address"));
+    }
+    Ok(ip)
+}
+
+/// 发送SSDP M-SEARCH请求，返回第一个响应的网关的设备描述地址 (LOCATION)
+fn discover(ssdp_address: Option<SocketAddr>, bind: &Bind) -> Result<String, Error> {
+    let target: SocketAddr = match ssdp_address {
+        Some(address) => address,
+        None => SSDP_ADDRESS.parse()?,
+    };
+    let socket = bind.udp_socket(false)?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDRESS}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {SEARCH_TARGET}\r\n\r\n"
+    );
+    socket.send_to(request.as_bytes(), target)?;
+
+    let deadline = Instant::now() + GATEWAY_TIMEOUT;
+    let mut buf = [0u8; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(anyhow!("no UPnP gateway found via SSDP"));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(_) => return Err(anyhow!("no UPnP gateway found via SSDP")),
+        };
+        let response = String::from_utf8_lossy(&buf[..len]);
+        let location = response.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("location")
+                .then(|| value.trim().to_string())
+        });
+        if let Some(location) = location {
+            return Ok(location);
+        }
+    }
+}
+
+/// 解析设备描述，返回URLBase和第一个WANIPConnection/WANPPPConnection服务
+fn parse_description(xml: &str) -> Result<(Option<String>, WanService), Error> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut url_base = None;
+    let mut current = String::new();
+    let mut service_type = String::new();
+    let mut control_url = String::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                current = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                if current == "service" {
+                    service_type.clear();
+                    control_url.clear();
+                }
+            }
+            Event::Text(text) => {
+                let text = text.decode()?.into_owned();
+                match current.as_str() {
+                    "URLBase" => url_base = Some(text),
+                    "serviceType" => service_type = text,
+                    "controlURL" => control_url = text,
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                if e.local_name().as_ref() == b"service"
+                    && (service_type.contains(":WANIPConnection:")
+                        || service_type.contains(":WANPPPConnection:"))
+                {
+                    return Ok((
+                        url_base,
+                        WanService {
+                            service_type,
+                            control_url,
+                        },
+                    ));
+                }
+                current.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Err(anyhow!("no WAN connection service in UPnP description"))
+}
+
+/// 查找XML中第一个指定元素的文本
+fn element_text(xml: &str, name: &str) -> Result<Option<String>, Error> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut found = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => found = e.local_name().as_ref() == name.as_bytes(),
+            Event::Text(text) if found => return Ok(Some(text.decode()?.into_owned())),
+            Event::End(_) if found => return Ok(Some(String::new())),
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+        <controlURL>/ctl/L3F</controlURL>
+      </service>
+    </serviceList>
+    <deviceList>
+      <device>
+        <serviceList>
+          <service>
+            <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+            <controlURL>/ctl/IPConn</controlURL>
+          </service>
+        </serviceList>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+    const SOAP_RESPONSE: &str = r#"<?xml version="1.0"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body>
+<u:GetExternalIPAddressResponse xmlns:u="urn:schemas-upnp-org:service:WANIPConnection:1">
+<NewExternalIPAddress>198.51.100.23</NewExternalIPAddress>
+</u:GetExternalIPAddressResponse></s:Body></s:Envelope>"#;
+
+    #[test]
+    fn test_upnp_external_ip() {
+        // 本地模拟网关：SSDP响应指向本地HTTP服务，HTTP服务依次返回设备描述和SOAP响应
+        let (http, http_handle) = serve_http(vec![DESCRIPTION, SOAP_RESPONSE]);
+        let location = format!("http://{http}/rootDesc.xml");
+        let ssdp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ssdp_address = ssdp.local_addr().unwrap();
+
+        let ssdp_handle = thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            let (len, peer) = ssdp.recv_from(&mut buf).unwrap();
+            assert!(String::from_utf8_lossy(&buf[..len]).starts_with("M-SEARCH"));
+            let response =
+                format!("HTTP/1.1 200 OK\r\nST: {SEARCH_TARGET}\r\nLOCATION: {location}\r\n\r\n");
+            ssdp.send_to(response.as_bytes(), peer).unwrap();
+        });
+
+        let config = UpnpConfig {
+            location: None,
+            ssdp_address: Some(ssdp_address),
+        };
+        assert_eq!(
+            external_ip(&config, &Bind::default()).unwrap().to_string(),
+            "198.51.100.23"
+        );
+        ssdp_handle.join().unwrap();
+        let requests = http_handle.join().unwrap();
+        assert!(requests[0].0.starts_with("GET /rootDesc.xml"));
+        assert!(requests[1].0.starts_with("POST /ctl/IPConn"));
+        assert!(
+            requests[1]
+                .0
+                .contains("WANIPConnection:1#GetExternalIPAddress")
+        );
+    }
+}
@@ -1,12 +1,42 @@
 use anyhow::Error;
 use log::{info, warn};
+use std::net::Ipv6Addr;
 
 // 子模块声明
+pub mod azure;
+pub mod bind;
 pub mod cloudflare;
+pub mod custom;
+pub mod desec;
+pub mod digitalocean;
 pub mod dnspod;
+pub mod duckdns;
+pub mod file;
+pub mod gandi;
+pub mod gcloud;
+pub mod hetzner;
+pub mod history;
+pub mod huaweicloud;
+pub mod ip_source;
+pub mod namecheap;
+#[cfg(target_os = "linux")]
+pub mod netlink;
+pub mod porkbun;
+pub mod powerdns;
+mod process;
+pub mod registry;
+#[cfg(target_os = "linux")]
+pub mod reload;
+pub mod retry;
+pub mod schedule;
+pub mod state;
+#[cfg(test)]
+mod test_support;
 
 // 重新导出常用类型
+pub(crate) use bind::http_client;
 pub use cloudflare::CloudflareProvider;
+pub use registry::{BatchContext, ProviderConfig, ProviderContext, ProviderRegistry};
 
 // 通用的DNS记录结构
 #[derive(Clone, Debug)]
@@ -16,10 +46,49 @@ pub struct DnsRecord {
     pub value: String,
     pub record_type: String,
 }
+
+/// 计算完整域名相对于zone的记录名称，不属于该zone时返回None
+/// 例如: ("sub.example.com", "example.com") -> Some("sub")
+///      ("example.com", "example.com") -> Some("@")
+///      ("sub.other.com", "example.com") -> None
+pub(crate) fn relative_name(full_domain: &str, zone: &str) -> Option<String> {
+    let full_domain = full_domain.trim_end_matches('.');
+    let zone = zone.trim_end_matches('.');
+    if full_domain.eq_ignore_ascii_case(zone) {
+        return Some("@".to_string());
+    }
+    let prefix_len = full_domain.len().checked_sub(zone.len() + 1)?;
+    let prefix = full_domain.get(..prefix_len)?;
+    let suffix = full_domain.get(prefix_len..)?;
+    if suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(zone) {
+        Some(prefix.to_string())
+    } else {
+        None
+    }
+}
+
+/// IP地址对应的记录类型，IPv6地址为 "AAAA"，其余为 "A"
+///
+/// 构造provider时据此设置其record_type，provider只查询和修改该类型的记录，
+/// 同名的另一地址族记录保持不变
+pub fn get_record_type(ip: &str) -> &'static str {
+    if ip.parse::<Ipv6Addr>().is_ok() {
+        "AAAA"
+    } else {
+        "A"
+    }
+}
+
+/// 一次更新的结果，record_id为provider中记录的ID (如有)
 pub enum DnsUpdateResult {
-    Changed { old_ip: String },
+    Changed {
+        old_ip: String,
+        record_id: Option<String>,
+    },
     Created,
-    Unchanged,
+    Unchanged {
+        record_id: Option<String>,
+    },
 }
 
 // DNS Provider trait - 所有DNS提供商必须实现这个trait
@@ -31,15 +100,18 @@ pub trait DnsProvider {
     fn update_dns_record(&self, current_ip: &str) -> Result<DnsUpdateResult, Error> {
         match self.get_record() {
             Ok(Some(record)) => {
+                // 部分provider (如hosts文件) 的记录没有ID
+                let record_id = Some(record.id.clone()).filter(|id| !id.is_empty());
                 if current_ip != record.value {
                     info!("ip changed from {} to {}", record.value, current_ip);
                     self.modify_record(current_ip, &record)?;
                     Ok(DnsUpdateResult::Changed {
                         old_ip: record.value,
+                        record_id,
                     })
                 } else {
                     info!("ip not changed");
-                    Ok(DnsUpdateResult::Unchanged)
+                    Ok(DnsUpdateResult::Unchanged { record_id })
                 }
             }
             Ok(None) => {
@@ -54,3 +126,44 @@ pub trait DnsProvider {
         }
     }
 }
+
+/// 批量更新同一zone下多个域名的provider，查询和修改在尽量少的API调用中完成
+pub trait BatchDnsProvider {
+    /// 将全部域名的记录更新为current_ip，按构造时的域名顺序返回每个域名的结果
+    fn update_dns_records(&self, current_ip: &str) -> Result<Vec<DnsUpdateResult>, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_name() {
+        assert_eq!(
+            relative_name("sub.example.com", "example.com"),
+            Some("sub".to_string())
+        );
+        assert_eq!(
+            relative_name("api.v2.example.com", "example.com"),
+            Some("api.v2".to_string())
+        );
+        assert_eq!(
+            relative_name("example.com", "example.com"),
+            Some("@".to_string())
+        );
+        assert_eq!(
+            relative_name("www.example.com.", "example.com."),
+            Some("www".to_string())
+        );
+        assert_eq!(relative_name("sub.other.com", "example.com"), None);
+        assert_eq!(relative_name("badexample.com", "example.com"), None);
+        assert_eq!(relative_name("com", "example.com"), None);
+    }
+
+    #[test]
+    fn test_get_record_type() {
+        assert_eq!(get_record_type("1.2.3.4"), "A");
+        assert_eq!(get_record_type("2001:db8::1"), "AAAA");
+        assert_eq!(get_record_type("::ffff:1.2.3.4"), "AAAA");
+    }
+}
@@ -1,39 +1,44 @@
 #![cfg_attr(windows_subsystem, windows_subsystem = "windows")]
 use anyhow::{Error, anyhow};
 use askama::Template;
-use clap::Parser;
-use dns_lib::CloudflareProvider;
-use dns_lib::DnsProvider;
-use dns_lib::DnsUpdateResult;
-use dns_lib::dnspod::DnspodProvider;
-use log::{error, info};
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use clap::{Parser, Subcommand, ValueEnum};
+use dns_lib::bind::{self, Bind};
+use dns_lib::history::{self, History, HistoryEntry, HistoryEvent, Outcome};
+use dns_lib::ip_source::{self, IpSource};
+#[cfg(target_os = "linux")]
+use dns_lib::netlink;
+use dns_lib::registry::merge_params;
+#[cfg(target_os = "linux")]
+use dns_lib::reload;
+use dns_lib::retry::{self, ErrorKind, RetryConfig};
+use dns_lib::schedule::{self, CronWindow};
+use dns_lib::state::{DomainState, State, TargetState};
+use dns_lib::{BatchContext, ProviderContext, ProviderRegistry};
+use dns_lib::{DnsUpdateResult, get_record_type};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::fs;
+use std::net::{IpAddr, Ipv6Addr};
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::{LazyLock, OnceLock};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use telegram_bot_send::{DynError, TelegramBot, TelegramBotBuilder};
 use tokio::runtime::Runtime;
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-#[derive(Default)]
-enum Provider {
-    Dnspod,
-    #[default]
-    Cloudflare,
-}
-
 #[derive(Parser)]
 #[command(name = "ddns")]
 #[command(about = "A DNSPod DDNS client that supports multiple domains")]
 struct Args {
     /// Path to the configuration file
-    #[arg(short, long, default_value = "config.toml")]
+    #[arg(short, long, default_value = "config.toml", global = true)]
     config: PathBuf,
 
     /// Enable verbose logging
@@ -45,62 +50,597 @@ struct Args {
     tg_chat_id: Option<String>,
     #[arg(long)]
     tg_http_proxy: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Show detected IP changes and DNS updates recorded in the history file
+    History {
+        /// Only show entries of this domain
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Only show entries since this time, e.g. "2024-06-01", "2024-06-01 08:00" or "7d" (7 days ago)
+        #[arg(long, value_parser = history::parse_since)]
+        since: Option<DateTime<Local>>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+/// history命令的输出格式
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Config {
-    /// 间隔时间（秒）
+    /// 间隔时间（秒），域名未设置interval时使用
     #[serde(default = "default_sleep_secs")]
     sleep_secs: u64,
 
-    /// 每隔几次强制从dnspod获取最新的记录
+    /// 每隔几次强制从dnspod获取最新的记录，域名未设置force_refresh_interval时使用
     #[serde(default = "default_force_interval")]
     force_get_record_interval: i8,
 
-    /// 默认DNS Provider类型 ("dnspod" 或 "cloudflare")
-    #[serde(default)]
-    default_provider: Provider,
+    /// 是否监听网卡地址变化，地址变化时立即更新，默认为true (仅Linux，其他平台只按间隔轮询)
+    #[serde(default = "default_true")]
+    watch_address_changes: bool,
 
-    /// 默认DNSPod Token
+    /// 是否监听配置文件的修改，修改后自动重新加载，默认为false (仅Linux，SIGHUP始终会触发重新加载)
     #[serde(default)]
-    default_dnspod_token: Option<String>,
+    watch_config_file: bool,
 
-    /// 默认Cloudflare API Token
-    #[serde(default)]
-    default_cloudflare_token: Option<String>,
+    /// 默认DNS Provider类型，支持的类型见 ProviderRegistry::with_builtin，默认为 "cloudflare"
+    #[serde(default = "default_provider")]
+    default_provider: String,
 
     /// 默认查询IP的URL
     #[serde(default = "default_ip_url")]
     default_ip_url: String,
 
+    /// 默认获取IP的方式 (可选，如 { upnp = {} }，未设置时从default_ip_url查询)
+    #[serde(default)]
+    default_ip_source: Option<IpSource>,
+
     /// 默认IP变化时执行的hook指令
     #[serde(default)]
     default_hook_command: Option<String>,
 
+    /// 默认的部分目标更新失败处理策略 ("best_effort" 或 "require_all")
+    #[serde(default)]
+    default_partial_failure: PartialFailurePolicy,
+
+    /// 失败时的重试、退避和熔断配置
+    #[serde(default)]
+    retry: RetryConfig,
+
+    /// 状态文件路径，保存上次同步的IP和失败计数，重启后恢复，默认为 "ddns_state.json"，为空时不保存
+    #[serde(default = "default_state_file")]
+    state_file: String,
+
+    /// 历史文件路径，记录IP变化和DNS更新，默认为 "ddns_history.jsonl"，为空时不记录
+    #[serde(default = "default_history_file")]
+    history_file: String,
+
     /// 域名配置列表
     domains: Vec<DomainConfig>,
+
+    /// 各Provider的默认配置 (以 "default_" 开头的其余字段，如 default_cloudflare_token)
+    #[serde(flatten)]
+    provider_defaults: Map<String, Value>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 struct DomainConfig {
     /// DNS Provider类型 (可选，未设置时使用default_provider)
-    /// 支持: "dnspod" 或 "cloudflare"
-    provider: Option<Provider>,
+    provider: Option<String>,
 
-    /// DNSPod Token (可选，provider为dnspod时使用，未设置时使用default_dnspod_token)
-    dnspod_token: Option<String>,
+    /// 完整域名 (如: "sub.example.com" 或 "@.example.com" 表示根域名)，与names二选一
+    #[serde(default)]
+    domain: String,
 
-    /// Cloudflare API Token (可选，provider为cloudflare时使用，未设置时使用default_cloudflare_token)
-    cloudflare_token: Option<String>,
+    /// 同一zone下的多个记录名称 (如: ["@", "www", "vpn"]，"@" 表示zone本身)，需同时设置zone
+    #[serde(default)]
+    names: Vec<String>,
 
-    /// 完整域名 (如: "sub.example.com" 或 "@.example.com" 表示根域名)
-    domain: String,
+    /// names所属的zone (如: "example.com")
+    zone: Option<String>,
+
+    /// IPv6前缀模式 (可选)：检测当前的IPv6前缀，与interface_ids中的接口标识组合成各记录的AAAA地址
+    ipv6_prefix: Option<Ipv6PrefixConfig>,
+
+    /// 前缀模式下各记录名称的接口标识 (如: nas = "::1234:5678")，需同时设置zone，与names二选一
+    #[serde(default)]
+    interface_ids: BTreeMap<String, String>,
 
     /// 查询IP的URL (可选，未设置时使用default_ip_url)
     ip_url: Option<String>,
 
+    /// 获取IP的方式 (可选，优先于ip_url，未设置时依次使用ip_url、default_ip_source和default_ip_url)
+    ip_source: Option<IpSource>,
+
+    /// 获取IP时绑定的网卡 (可选，如 "pppoe-wan2"，仅Linux)，多WAN口时用于指定从哪条线路查询
+    bind_interface: Option<String>,
+
+    /// 获取IP时绑定的源地址 (可选，需为本机网卡上的地址)
+    bind_address: Option<IpAddr>,
+
+    /// provider的API请求是否同样使用bind_interface和bind_address，默认为false
+    #[serde(default)]
+    bind_providers: bool,
+
+    /// 检查间隔 (可选，如 300 或 "5m"，未设置时使用sleep_secs)
+    #[serde(default, deserialize_with = "schedule::deserialize_optional_duration")]
+    interval: Option<Duration>,
+
+    /// 强制从provider获取最新记录的间隔 (可选，如 "1h"，未设置时为interval的force_get_record_interval倍)
+    #[serde(default, deserialize_with = "schedule::deserialize_optional_duration")]
+    force_refresh_interval: Option<Duration>,
+
+    /// 允许检查的时段 (可选，类cron表达式 "分 时 日 月 周"，如 ["* 8-23 * * *"]，满足任一即可)
+    #[serde(default)]
+    windows: Vec<CronWindow>,
+
     /// IP变化时执行的hook指令 (可选，未设置时使用default_hook_command)
     hook_command: Option<String>,
+
+    /// 多个更新目标 (可选，设置后检测到的IP会同步更新到每个目标，未设置时只使用provider)
+    #[serde(default)]
+    targets: Vec<TargetConfig>,
+
+    /// 部分目标更新失败时的处理策略 (可选，未设置时使用default_partial_failure)
+    partial_failure: Option<PartialFailurePolicy>,
+
+    /// Provider的配置 (其余字段，如 cloudflare_token，未设置时使用对应的 "default_" 配置)
+    #[serde(flatten)]
+    provider_params: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct TargetConfig {
+    /// DNS Provider类型 (可选，未设置时使用域名的provider)
+    provider: Option<String>,
+
+    /// Provider的配置 (未设置的项依次使用域名中的配置和 "default_" 配置)
+    #[serde(flatten)]
+    provider_params: Map<String, Value>,
+}
+
+/// IPv6前缀模式的配置
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct Ipv6PrefixConfig {
+    /// 从该网卡的全局IPv6地址获取前缀 (可选，未设置时从ip_url获取IPv6地址)
+    interface: Option<String>,
+
+    /// 前缀长度，默认为64
+    #[serde(default = "default_prefix_length")]
+    length: u8,
+}
+
+/// 部分目标更新失败时的处理策略
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+enum PartialFailurePolicy {
+    /// 成功的目标照常发送通知和执行hook，失败的目标在下一轮重试
+    #[default]
+    BestEffort,
+    /// 所有目标都更新成功后才发送通知和执行hook，失败的目标在下一轮重试
+    RequireAll,
+}
+
+/// 解析后的更新目标
+#[derive(PartialEq)]
+struct Target {
+    /// 目标在域名配置中的序号
+    index: usize,
+    provider: String,
+    params: Map<String, Value>,
+}
+
+impl Target {
+    /// 域名某一类型的记录在该目标上的状态标识，如 "home.example.com/AAAA/cloudflare#0"
+    fn key(&self, domain: &str, record_type: &str) -> String {
+        format!(
+            "{}/{}#{}",
+            record_key(domain, record_type),
+            self.provider,
+            self.index
+        )
+    }
+}
+
+/// 同一域名的A和AAAA记录分别保存状态
+const RECORD_TYPES: [&str; 2] = ["A", "AAAA"];
+
+/// 完整域名某一类型记录的状态标识，如 "home.example.com/AAAA"
+fn record_key(domain: &str, record_type: &str) -> String {
+    format!("{domain}/{record_type}")
+}
+
+/// 唤醒主循环的事件，只在Linux上产生
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+enum Event {
+    /// 网卡的全局地址发生变化
+    AddressChanged,
+    /// 收到SIGHUP或配置文件被修改
+    Reload,
+}
+
+/// 重新加载配置时域名配置的变化
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ConfigDiff {
+    added: usize,
+    changed: usize,
+    removed: usize,
+    unchanged: usize,
+}
+
+/// 单个域名配置的调度状态
+struct DomainSchedule {
+    /// 下一次检查的时间
+    next_check: Instant,
+    /// 最近一次强制获取记录的时间
+    last_force_refresh: Option<DateTime<Local>>,
+    /// 连续失败的次数，决定退避的间隔
+    failures: u32,
+    /// 连续认证失败的次数，达到阈值后暂停该域名
+    auth_failures: u32,
+    /// 是否因认证失败而暂停，暂停期间地址变化也不触发检查
+    paused: bool,
+}
+
+impl DomainSchedule {
+    /// 根据状态文件中保存的状态恢复调度，退避或暂停中的域名到retry_at再检查，其余立即检查
+    fn restore(domain_config: &DomainConfig, saved: Option<&DomainState>) -> Self {
+        let saved = saved.cloned().unwrap_or_default();
+        let delay = saved
+            .retry_at
+            .and_then(|time| (time - Local::now()).to_std().ok())
+            .unwrap_or_default();
+        DomainSchedule {
+            next_check: domain_config.next_check(delay),
+            last_force_refresh: saved.last_force_refresh,
+            failures: saved.failures,
+            auth_failures: saved.auth_failures,
+            paused: saved.paused,
+        }
+    }
+
+    /// 需要写入状态文件的调度状态
+    fn save(&self) -> DomainState {
+        let retry_at = (self.failures > 0).then(|| {
+            let delay = self.next_check.saturating_duration_since(Instant::now());
+            Local::now() + ChronoDuration::from_std(delay).unwrap_or_default()
+        });
+        DomainState {
+            last_force_refresh: self.last_force_refresh,
+            failures: self.failures,
+            auth_failures: self.auth_failures,
+            paused: self.paused,
+            retry_at,
+        }
+    }
+
+    /// 根据本次检查的结果更新失败计数，返回距下一次检查的间隔
+    fn record_outcome(
+        &mut self,
+        retry: &RetryConfig,
+        interval: Duration,
+        label: &str,
+        failure: Option<ErrorKind>,
+    ) -> Duration {
+        let Some(kind) = failure else {
+            if self.failures > 0 {
+                info!("{label} recovered after {} failed checks", self.failures);
+            }
+            self.failures = 0;
+            self.auth_failures = 0;
+            self.paused = false;
+            return interval;
+        };
+
+        self.failures += 1;
+        self.auth_failures = if kind == ErrorKind::Auth {
+            self.auth_failures + 1
+        } else {
+            0
+        };
+        if retry.auth_failure_threshold > 0 && self.auth_failures >= retry.auth_failure_threshold {
+            self.paused = true;
+            error!(
+                "{label} paused for {}s after {} consecutive authentication failures, please check the provider credentials",
+                retry.auth_failure_pause.as_secs(),
+                self.auth_failures
+            );
+            return retry.auth_failure_pause;
+        }
+        let delay = retry.backoff(interval, self.failures);
+        warn!(
+            "{label} failed {} times in a row ({kind:?}), next check in {}s",
+            self.failures,
+            delay.as_secs()
+        );
+        delay
+    }
+}
+
+impl DomainConfig {
+    /// zone下的记录名称，前缀模式下为interface_ids中的名称
+    fn names(&self) -> Vec<&str> {
+        if self.names.is_empty() {
+            self.interface_ids.keys().map(String::as_str).collect()
+        } else {
+            self.names.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// 配置对应的全部完整域名
+    fn domains(&self) -> Vec<String> {
+        let Some(zone) = &self.zone else {
+            return vec![self.domain.clone()];
+        };
+        self.names()
+            .into_iter()
+            .map(|name| match name {
+                "@" => zone.clone(),
+                name => format!("{name}.{zone}"),
+            })
+            .collect()
+    }
+
+    /// 每个完整域名及其应当指向的IP，前缀模式下为当前前缀与各接口标识的组合
+    fn records(&self, current_ip: &str) -> Result<Vec<(String, String)>, Error> {
+        let Some(prefix) = &self.ipv6_prefix else {
+            return Ok(self
+                .domains()
+                .into_iter()
+                .map(|domain| (domain, current_ip.to_string()))
+                .collect());
+        };
+        let current: Ipv6Addr = current_ip
+            .parse()
+            .map_err(|_| anyhow!("ipv6_prefix requires an IPv6 address, got {current_ip}"))?;
+        self.domains()
+            .into_iter()
+            .zip(self.interface_ids.values())
+            .map(|(domain, interface_id)| {
+                let interface_id: Ipv6Addr = interface_id.parse()?;
+                let ip = ip_source::combine_prefix(current, prefix.length, interface_id);
+                Ok((domain, ip.to_string()))
+            })
+            .collect()
+    }
+
+    /// 出站请求绑定的网卡和源地址
+    fn bind(&self) -> Bind {
+        Bind {
+            interface: self.bind_interface.clone(),
+            address: self.bind_address,
+        }
+    }
+
+    /// 日志中使用的名称，如 "example.com" 或 "example.com [@, www, vpn]"
+    fn label(&self) -> String {
+        match &self.zone {
+            Some(zone) => format!("{} [{}]", zone, self.names().join(", ")),
+            None => self.domain.clone(),
+        }
+    }
+
+    /// time是否处于允许检查的时段内，未设置时段时总是true
+    fn in_windows(&self, time: DateTime<Local>) -> bool {
+        schedule::next_in_windows(&self.windows, time) == Some(time)
+    }
+
+    /// 从现在起经过delay后的下一次检查时间，设置了时段时推迟到时段内
+    fn next_check(&self, delay: Duration) -> Instant {
+        let next = Instant::now() + delay;
+        let Ok(wall_delay) = ChronoDuration::from_std(delay) else {
+            return next;
+        };
+        let wall = Local::now() + wall_delay;
+        match schedule::next_in_windows(&self.windows, wall) {
+            Some(time) => next + (time - wall).to_std().unwrap_or_default(),
+            None => next,
+        }
+    }
+
+    /// 校验domain与names/interface_ids/zone的组合，以及前缀模式、绑定和调度的配置
+    fn validate(&self) -> Result<(), Error> {
+        self.bind().validate()?;
+        if self.interval.is_some_and(|interval| interval.is_zero()) {
+            return Err(anyhow!("interval must be greater than 0"));
+        }
+        if self
+            .force_refresh_interval
+            .is_some_and(|interval| interval.is_zero())
+        {
+            return Err(anyhow!("force_refresh_interval must be greater than 0"));
+        }
+        if schedule::next_in_windows(&self.windows, Local::now()).is_none() {
+            return Err(anyhow!("windows never match any time"));
+        }
+        if self.bind_providers && self.bind().is_unbound() {
+            return Err(anyhow!(
+                "bind_providers requires bind_interface or bind_address"
+            ));
+        }
+
+        if !self.names.is_empty() && !self.interface_ids.is_empty() {
+            return Err(anyhow!("names and interface_ids cannot be set together"));
+        }
+        let has_names = !self.names.is_empty() || !self.interface_ids.is_empty();
+        match (self.domain.is_empty(), has_names, &self.zone) {
+            (false, false, None) | (true, true, Some(_)) => {}
+            (true, false, _) => return Err(anyhow!("either domain or names is required")),
+            (false, true, _) => return Err(anyhow!("domain and names cannot be set together")),
+            (_, true, None) => return Err(anyhow!("zone is required when names is set")),
+            (_, false, Some(_)) => return Err(anyhow!("zone can only be used with names")),
+        }
+
+        match &self.ipv6_prefix {
+            Some(prefix) => {
+                if self.interface_ids.is_empty() {
+                    return Err(anyhow!("interface_ids is required when ipv6_prefix is set"));
+                }
+                if !(1..=128).contains(&prefix.length) {
+                    return Err(anyhow!(
+                        "ipv6_prefix length must be between 1 and 128, got {}",
+                        prefix.length
+                    ));
+                }
+                for (name, interface_id) in &self.interface_ids {
+                    interface_id
+                        .parse::<Ipv6Addr>()
+                        .map_err(|_| anyhow!("invalid interface id for {name}: {interface_id}"))?;
+                }
+                Ok(())
+            }
+            None if !self.interface_ids.is_empty() => {
+                Err(anyhow!("interface_ids can only be used with ipv6_prefix"))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Config {
+    /// 域名的IP来源
+    fn ip_source(&self, domain_config: &DomainConfig) -> IpSource {
+        if let Some(source) = &domain_config.ip_source {
+            return source.clone();
+        }
+        if let Some(ip_url) = &domain_config.ip_url {
+            return IpSource::Http(ip_url.clone());
+        }
+        self.default_ip_source
+            .clone()
+            .unwrap_or_else(|| IpSource::Http(self.default_ip_url.clone()))
+    }
+
+    /// 域名的检查间隔
+    fn interval(&self, domain_config: &DomainConfig) -> Duration {
+        domain_config
+            .interval
+            .unwrap_or(Duration::from_secs(self.sleep_secs))
+    }
+
+    /// 域名强制获取记录的间隔，未设置时为每隔force_get_record_interval次检查强制获取一次
+    fn force_refresh_interval(&self, domain_config: &DomainConfig) -> Duration {
+        domain_config.force_refresh_interval.unwrap_or_else(|| {
+            self.interval(domain_config) * self.force_get_record_interval.max(1) as u32
+        })
+    }
+
+    /// 域名生效的检查间隔、强制获取间隔和时段，重新加载时只有这些变化才重置调度状态
+    fn schedule_settings(
+        &self,
+        domain_config: &DomainConfig,
+    ) -> (Duration, Duration, Vec<CronWindow>) {
+        (
+            self.interval(domain_config),
+            self.force_refresh_interval(domain_config),
+            domain_config.windows.clone(),
+        )
+    }
+
+    /// 域名的全部更新目标，未配置targets时为provider对应的单个目标
+    fn targets(&self, domain_config: &DomainConfig) -> Vec<Target> {
+        let provider = domain_config
+            .provider
+            .as_deref()
+            .unwrap_or(&self.default_provider);
+        let params = merge_params(&self.provider_defaults, &domain_config.provider_params);
+        if domain_config.targets.is_empty() {
+            return vec![Target {
+                index: 0,
+                provider: provider.to_string(),
+                params,
+            }];
+        }
+
+        domain_config
+            .targets
+            .iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let provider = target.provider.as_deref().unwrap_or(provider);
+                let mut params = params.clone();
+                params.extend(target.provider_params.clone());
+                Target {
+                    index: i,
+                    provider: provider.to_string(),
+                    params,
+                }
+            })
+            .collect()
+    }
+
+    /// 全部更新目标的状态标识
+    fn target_keys(&self) -> Vec<String> {
+        self.domains
+            .iter()
+            .flat_map(|domain_config| {
+                let targets = self.targets(domain_config);
+                domain_config.domains().into_iter().flat_map(move |domain| {
+                    targets
+                        .iter()
+                        .flat_map(|target| {
+                            RECORD_TYPES.map(|record_type| target.key(&domain, record_type))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect()
+    }
+
+    /// 全部域名配置的状态标识
+    fn domain_keys(&self) -> Vec<String> {
+        self.domains.iter().map(DomainConfig::label).collect()
+    }
+
+    /// 全部域名配置中每个完整域名各类型记录的状态标识
+    fn record_keys(&self) -> Vec<String> {
+        self.domains
+            .iter()
+            .flat_map(DomainConfig::domains)
+            .flat_map(|domain| RECORD_TYPES.map(|record_type| record_key(&domain, record_type)))
+            .collect()
+    }
+
+    /// 除域名列表外的全局配置，用于判断重新加载时是否有变化
+    fn global_settings(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(map) = value.as_object_mut() {
+            map.remove("domains");
+        }
+        value
+    }
+
+    /// 追加一条历史记录，未配置历史文件时忽略，写入失败只记录日志
+    fn record_history(&self, entry: HistoryEntry) {
+        if self.history_file.is_empty() {
+            return;
+        }
+        if let Err(e) = History::new(&self.history_file).append(&entry) {
+            warn!("Failed to record history: {e}");
+        }
+    }
+}
+
+fn default_provider() -> String {
+    "cloudflare".to_string()
 }
 
 fn default_sleep_secs() -> u64 {
@@ -111,52 +651,34 @@ fn default_force_interval() -> i8 {
     5
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_prefix_length() -> u8 {
+    64
+}
+
 fn default_ip_url() -> String {
     "http://whatismyip.akamai.com".to_string()
 }
 
-// 全局静态HTTP客户端，禁用代理
-static HTTP_CLIENT: LazyLock<reqwest::blocking::Client> = LazyLock::new(|| {
-    reqwest::blocking::Client::builder()
-        .no_proxy()
-        .build()
-        .expect("Failed to create HTTP client")
-});
+fn default_state_file() -> String {
+    "ddns_state.json".to_string()
+}
 
-/// 解析完整域名，返回(子域名, 主域名)
-/// 支持多级子域名：
-/// 例如: "sub.example.com" -> ("sub", "example.com")
-///      "api.v2.example.com" -> ("api.v2", "example.com")
-///      "deep.nested.sub.example.com" -> ("deep.nested.sub", "example.com")
-///      "@.example.com" -> ("@", "example.com")
-///      "example.com" -> ("@", "example.com")
-fn parse_domain(full_domain: &str) -> Result<(String, String), Error> {
-    let parts: Vec<&str> = full_domain.split('.').collect();
-
-    if parts.len() < 2 {
-        return Err(anyhow!("Invalid domain format: {}", full_domain));
-    }
-
-    if let Some(main_domain) = full_domain.strip_prefix("@.") {
-        // @.example.com -> ("@", "example.com")
-        Ok(("@".to_string(), main_domain.to_string()))
-    } else if parts.len() == 2 {
-        // example.com -> ("@", "example.com")
-        Ok(("@".to_string(), full_domain.to_string()))
-    } else {
-        // 对于多级域名，假设最后两个部分是主域名，其余为子域名
-        // sub.example.com -> ("sub", "example.com")
-        // api.v2.example.com -> ("api.v2", "example.com")
-        // deep.nested.sub.example.com -> ("deep.nested.sub", "example.com")
-        let main_domain_parts = &parts[parts.len() - 2..];
-        let subdomain_parts = &parts[..parts.len() - 2];
+fn default_history_file() -> String {
+    "ddns_history.jsonl".to_string()
+}
 
-        let main_domain = main_domain_parts.join(".");
-        let subdomain = subdomain_parts.join(".");
+/// 地址变化后等待没有新事件的时间，之后开始更新
+const ADDRESS_CHANGE_DEBOUNCE: Duration = Duration::from_secs(3);
 
-        Ok((subdomain, main_domain))
-    }
-}
+/// 地址持续变化时，从第一个事件起最多等待的时间
+const ADDRESS_CHANGE_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// 全局Provider注册表，新增Provider时在dns_lib中注册即可
+static PROVIDERS: LazyLock<ProviderRegistry> = LazyLock::new(ProviderRegistry::with_builtin);
 
 /// 执行hook指令
 fn execute_hook_command(
@@ -222,61 +744,72 @@ fn execute_hook_command(
     }
 }
 
-/// 获取当前IP地址
-fn current_ip(ip_url: &str) -> Result<String, Error> {
-    let result = HTTP_CLIENT.get(ip_url).send();
-    match result {
-        Ok(ip) => match ip.text() {
-            Ok(text) => Ok(text.trim().to_string()),
-            Err(e) => Err(anyhow!(e)),
-        },
-        Err(e) => Err(anyhow!(e)),
+/// 获取域名配置的当前IP，前缀模式下配置了网卡时从网卡读取IPv6地址，否则从IP来源查询
+fn domain_current_ip(config: &Config, domain_config: &DomainConfig) -> Result<String, Error> {
+    if let Some(Ipv6PrefixConfig {
+        interface: Some(interface),
+        ..
+    }) = &domain_config.ipv6_prefix
+    {
+        return Ok(ip_source::interface_ipv6(interface)?.to_string());
     }
+
+    let source = config.ip_source(domain_config);
+    source
+        .current_ip(&domain_config.bind())
+        .map_err(|e| anyhow!("failed to get IP from {source}: {e}"))
 }
 
 /// 读取配置文件
 fn load_config(config_path: &PathBuf) -> Result<Config, Error> {
     let config_content = fs::read_to_string(config_path)
         .map_err(|e| anyhow!("Failed to read config file {:?}: {}", config_path, e))?;
+    parse_config(&config_content)
+}
 
-    let config: Config = toml::from_str(&config_content)
+/// 解析并验证配置内容
+fn parse_config(config_content: &str) -> Result<Config, Error> {
+    let config: Config = toml::from_str(config_content)
         .map_err(|e| anyhow!("Failed to parse config file: {}", e))?;
 
     if config.domains.is_empty() {
         return Err(anyhow!("No domains configured"));
     }
+    if config.sleep_secs == 0 {
+        return Err(anyhow!("sleep_secs must be greater than 0"));
+    }
+
+    if let Some(source) = &config.default_ip_source {
+        source
+            .validate()
+            .map_err(|e| anyhow!("default_ip_source is invalid: {}", e))?;
+    }
 
     // 验证每个域名配置
     for (i, domain_config) in config.domains.iter().enumerate() {
-        let provider = domain_config.provider.unwrap_or(config.default_provider);
-
-        // 检查DNSPod配置
-        if provider == Provider::Dnspod
-            && domain_config.dnspod_token.is_none()
-            && config.default_dnspod_token.is_none()
-        {
-            return Err(anyhow!(
-                "Domain {} uses DNSPod but has no dnspod_token and no default_dnspod_token is configured",
-                i + 1
-            ));
-        }
-
-        // 检查Cloudflare配置
-        if provider == Provider::Cloudflare
-            && domain_config.cloudflare_token.is_none()
-            && config.default_cloudflare_token.is_none()
-        {
-            return Err(anyhow!(
-                "Domain {} uses Cloudflare but has no cloudflare_token and no default_cloudflare_token is configured",
-                i + 1
-            ));
-        }
-
-        // 验证域名格式（仅DNSPod需要分割域名）
-        if provider == Provider::Dnspod
-            && let Err(e) = parse_domain(&domain_config.domain)
-        {
-            return Err(anyhow!("Domain {} has invalid format: {}", i + 1, e));
+        domain_config
+            .validate()
+            .map_err(|e| anyhow!("Domain {} is invalid: {}", i + 1, e))?;
+        config
+            .ip_source(domain_config)
+            .validate()
+            .map_err(|e| anyhow!("Domain {} has invalid ip source: {}", i + 1, e))?;
+        for target in config.targets(domain_config) {
+            for domain in domain_config.domains() {
+                PROVIDERS
+                    .validate(&target.provider, &target.params, &domain)
+                    .map_err(|e| anyhow!("Domain {} has invalid provider config: {}", i + 1, e))?;
+            }
+            // 前缀模式只更新AAAA记录，不能覆盖同名的A记录
+            if domain_config.ipv6_prefix.is_some()
+                && !PROVIDERS.selects_by_family(&target.provider)?
+            {
+                return Err(anyhow!(
+                    "Domain {} is invalid: provider {} does not support ipv6_prefix",
+                    i + 1,
+                    target.provider
+                ));
+            }
         }
     }
 
@@ -289,49 +822,259 @@ struct DomainUpdateResult {
     old_ip: String,
 }
 
-/// 处理单个域名的DDNS更新
-fn update_record_if_need(
-    domain_config: &DomainConfig,
-    config: &Config,
+/// 处理单个域名在一个目标上的DDNS更新
+fn update_record(
+    domain: &str,
+    target: &Target,
     current_ip: &str,
-    old_ip: &str,
-    get_current_record_from_authority: bool,
 ) -> Result<DnsUpdateResult, Error> {
-    let domain = domain_config.domain.clone();
-    if current_ip != old_ip || get_current_record_from_authority {
-        // 获取provider类型
-        let provider = domain_config.provider.unwrap_or(config.default_provider);
-        match provider {
-            Provider::Dnspod => {
-                // DNSPod provider
-                let (subdomain, main_domain) = parse_domain(&domain_config.domain)?;
-                let token = domain_config
-                    .dnspod_token
-                    .as_ref()
-                    .or(config.default_dnspod_token.as_ref())
-                    .ok_or_else(|| anyhow!("No DNSPod token available for domain {}", domain))?;
-
-                let provider: DnspodProvider =
-                    DnspodProvider::new(token.clone(), main_domain, subdomain);
-                Ok(provider.update_dns_record(current_ip)?)
+    // 根据provider类型和配置构造provider
+    let provider = PROVIDERS.create(
+        &target.provider,
+        &target.params,
+        &ProviderContext { domain, current_ip },
+    )?;
+    provider.update_dns_record(current_ip)
+}
+
+/// 处理多个域名在一个目标上的DDNS更新，provider支持批量更新时合并为一次调用，否则逐个域名更新
+///
+/// 遇到临时性错误时按retry配置立即重试
+fn update_target(
+    domains: &[String],
+    target: &Target,
+    current_ip: &str,
+    retry: &RetryConfig,
+) -> Vec<Result<DnsUpdateResult, Error>> {
+    if domains.len() > 1 {
+        let batch = retry.retry_transient(|| {
+            PROVIDERS
+                .create_batch(
+                    &target.provider,
+                    &target.params,
+                    &BatchContext {
+                        domains,
+                        current_ip,
+                    },
+                )
+                .and_then(|provider| {
+                    provider
+                        .map(|provider| provider.update_dns_records(current_ip))
+                        .transpose()
+                })
+        });
+        match batch {
+            Ok(Some(results)) if results.len() == domains.len() => {
+                return results.into_iter().map(Ok).collect();
             }
-            Provider::Cloudflare => {
-                // Cloudflare provider
-                let token = domain_config
-                    .cloudflare_token
-                    .as_ref()
-                    .or(config.default_cloudflare_token.as_ref())
-                    .ok_or_else(|| {
-                        anyhow!("No Cloudflare token available for domain {}", domain)
-                    })?;
-
-                let provider = CloudflareProvider::new(token.clone(), domain_config.domain.clone());
-                Ok(provider.update_dns_record(current_ip)?)
+            Ok(Some(results)) => {
+                let e = anyhow!(
+                    "batch update returned {} results for {} domains",
+                    results.len(),
+                    domains.len()
+                );
+                return domains.iter().map(|_| Err(anyhow!("{e}"))).collect();
             }
+            Ok(None) => {}
+            Err(e) => return domains.iter().map(|_| Err(anyhow!("{e:#}"))).collect(),
         }
+    }
+    domains
+        .iter()
+        .map(|domain| retry.retry_transient(|| update_record(domain, target, current_ip)))
+        .collect()
+}
+
+/// 将每个域名的IP同步到全部目标，域名有目标发生变更且满足部分失败策略时发送通知和执行hook
+///
+/// records为(完整域名, IP)列表，IP相同的域名在支持批量更新的provider上合并更新，
+/// 有目标更新失败时返回其中最严重的错误类型
+fn update_domain(
+    args: &Args,
+    config: &Config,
+    domain_config: &DomainConfig,
+    records: &[(String, String)],
+    statuses: &mut BTreeMap<String, TargetState>,
+    get_current_record_from_authority: bool,
+) -> Option<ErrorKind> {
+    let targets = config.targets(domain_config);
+    let provider_bind = if domain_config.bind_providers {
+        domain_config.bind()
     } else {
-        info!("IP for {domain} unchanged: {current_ip}");
-        Ok(DnsUpdateResult::Unchanged)
+        Bind::default()
+    };
+    // 每个域名有目标发生变更时记录其旧IP，以及更新失败的目标
+    let mut changed_from: Vec<Option<String>> = vec![None; records.len()];
+    let mut failed: Vec<Vec<&str>> = vec![Vec::new(); records.len()];
+    let mut failure = None;
+
+    for target in &targets {
+        // IP未变化且不需要强制获取记录的域名跳过，其余按IP分组
+        let mut pending: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, (domain, current_ip)) in records.iter().enumerate() {
+            let status = statuses
+                .entry(target.key(domain, get_record_type(current_ip)))
+                .or_default();
+            if *current_ip != status.last_ip || get_current_record_from_authority {
+                pending.entry(current_ip).or_default().push(i);
+            } else {
+                info!(
+                    "IP for {domain} on {} unchanged: {current_ip}",
+                    target.provider
+                );
+            }
+        }
+
+        for (current_ip, indexes) in pending {
+            let domains: Vec<String> = indexes.iter().map(|&i| records[i].0.clone()).collect();
+            let results = bind::with_provider_bind(&provider_bind, || {
+                update_target(&domains, target, current_ip, &config.retry)
+            });
+            for (i, result) in indexes.into_iter().zip(results) {
+                let last_ip =
+                    &statuses[&target.key(&records[i].0, get_record_type(current_ip))].last_ip;
+                if let Some(entry) =
+                    history_entry(target, &records[i].0, current_ip, last_ip, &result)
+                {
+                    config.record_history(entry);
+                }
+                let kind = update_status(
+                    statuses,
+                    target,
+                    &records[i].0,
+                    current_ip,
+                    result,
+                    &mut changed_from[i],
+                    &mut failed[i],
+                );
+                failure = failure.max(kind);
+            }
+        }
+    }
+
+    if targets.len() > 1 {
+        for (domain, current_ip) in records {
+            let record_type = get_record_type(current_ip);
+            let summary: Vec<String> = targets
+                .iter()
+                .map(
+                    |target| match &statuses[&target.key(domain, record_type)].last_error {
+                        None => format!("{}=ok", target.provider),
+                        Some(e) => format!("{}=failed({})", target.provider, e),
+                    },
+                )
+                .collect();
+            info!("Targets of {domain}: {}", summary.join(", "));
+        }
+    }
+
+    let policy = domain_config
+        .partial_failure
+        .unwrap_or(config.default_partial_failure);
+    for (((domain, current_ip), old_ip), failed) in records.iter().zip(changed_from).zip(failed) {
+        let Some(old_ip) = old_ip else {
+            continue;
+        };
+        if !failed.is_empty() && policy == PartialFailurePolicy::RequireAll {
+            warn!(
+                "Skip notification and hook for {domain}: targets {} failed and partial_failure is require_all",
+                failed.join(", ")
+            );
+            continue;
+        }
+
+        let result = DomainUpdateResult {
+            domain: domain.clone(),
+            new_ip: current_ip.clone(),
+            old_ip,
+        };
+        send_tg(args, &result);
+        exec_hook_if_present(config, domain_config, domain, result);
+    }
+    failure
+}
+
+/// 域名在一个目标上的更新结果对应的历史记录，记录未变化时返回None
+fn history_entry(
+    target: &Target,
+    domain: &str,
+    current_ip: &str,
+    last_ip: &str,
+    result: &Result<DnsUpdateResult, Error>,
+) -> Option<HistoryEntry> {
+    let (old_ip, outcome, error) = match result {
+        Ok(DnsUpdateResult::Changed { old_ip, .. }) => {
+            (Some(old_ip.clone()), Outcome::Success, None)
+        }
+        Ok(DnsUpdateResult::Created) => (None, Outcome::Success, None),
+        Ok(DnsUpdateResult::Unchanged { .. }) => return None,
+        // 更新失败时无法得知记录当前的IP，使用上次同步的IP
+        Err(e) => (
+            Some(last_ip.to_string()).filter(|ip| !ip.is_empty()),
+            Outcome::Failed,
+            Some(e.to_string()),
+        ),
+    };
+    Some(HistoryEntry {
+        time: Local::now(),
+        event: HistoryEvent::DnsUpdate,
+        domain: domain.to_string(),
+        provider: Some(target.provider.clone()),
+        old_ip,
+        new_ip: current_ip.to_string(),
+        outcome: Some(outcome),
+        error,
+    })
+}
+
+/// 记录域名在一个目标上的更新结果，发生变更时记录旧IP，失败时记录目标并返回错误类型
+fn update_status<'a>(
+    statuses: &mut BTreeMap<String, TargetState>,
+    target: &'a Target,
+    domain: &str,
+    current_ip: &str,
+    result: Result<DnsUpdateResult, Error>,
+    changed_from: &mut Option<String>,
+    failed: &mut Vec<&'a str>,
+) -> Option<ErrorKind> {
+    let status = statuses
+        .entry(target.key(domain, get_record_type(current_ip)))
+        .or_default();
+    match result {
+        Ok(result) => {
+            status.last_ip = current_ip.to_string();
+            status.last_success = Some(Local::now());
+            status.last_error = None;
+            status.failures = 0;
+            match result {
+                DnsUpdateResult::Changed { old_ip, record_id } => {
+                    status.record_id = record_id;
+                    info!(
+                        "{domain} on {} changed from {old_ip} to {current_ip}",
+                        target.provider
+                    );
+                    changed_from.get_or_insert(old_ip);
+                }
+                DnsUpdateResult::Created => {
+                    info!("{domain} on {} created with {current_ip}", target.provider);
+                    status.record_id = None;
+                    changed_from.get_or_insert_with(String::new);
+                }
+                DnsUpdateResult::Unchanged { record_id } => status.record_id = record_id,
+            }
+            None
+        }
+        Err(e) => {
+            error!(
+                "Error updating domain {} on {}: {}",
+                domain, target.provider, e
+            );
+            status.last_failure = Some(Local::now());
+            status.last_error = Some(e.to_string());
+            status.failures += 1;
+            failed.push(target.provider.as_str());
+            Some(retry::classify(&e))
+        }
     }
 }
 
@@ -348,6 +1091,15 @@ static TOKIO_RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
 fn main() -> Result<(), Error> {
     let args = Args::parse();
 
+    if let Some(Commands::History {
+        domain,
+        since,
+        format,
+    }) = &args.command
+    {
+        return show_history(&args.config, domain.as_deref(), *since, *format);
+    }
+
     // 初始化日志
     log_x::init_log("log", "dnspod.log", "info")?;
 
@@ -356,87 +1108,406 @@ fn main() -> Result<(), Error> {
     }
 
     // 加载配置文件
-    let config = load_config(&args.config)?;
+    let mut config = load_config(&args.config)?;
     info!("Loaded configuration with {} domains", config.domains.len());
 
-    // 为每个域名存储最新的IP
-    let mut latest_ips: std::collections::HashMap<String, String> =
-        std::collections::HashMap::new();
+    // 从状态文件恢复每个更新目标和域名配置的状态，读取失败时从空状态开始
+    let mut state = if config.state_file.is_empty() {
+        State::default()
+    } else {
+        State::load(Path::new(&config.state_file)).unwrap_or_else(|e| {
+            warn!("Ignoring state file: {e}");
+            State::default()
+        })
+    };
+    state.retain(
+        &config.target_keys(),
+        &config.domain_keys(),
+        &config.record_keys(),
+    );
+    let mut saved_state = state.clone();
+
+    // 地址变化和重新加载配置的事件，都未开启或订阅失败时只按间隔轮询
+    let (sender, receiver) = std::sync::mpsc::channel();
+    #[cfg(target_os = "linux")]
+    let watching = {
+        let address = config.watch_address_changes && watch_address_changes(sender.clone());
+        let reload = watch_reload(&args.config, config.watch_config_file, sender);
+        address || reload
+    };
+    #[cfg(not(target_os = "linux"))]
+    let watching = {
+        drop(sender);
+        false
+    };
+    let mut wakeups = watching.then_some(receiver);
 
-    let mut iteration = 0;
+    // 每个域名配置按各自的间隔和时段调度，启动时处于时段内且不在退避中的域名立即检查
+    let mut schedules: Vec<DomainSchedule> = config
+        .domains
+        .iter()
+        .map(|domain_config| {
+            DomainSchedule::restore(domain_config, state.domains.get(&domain_config.label()))
+        })
+        .collect();
 
     loop {
-        let get_current_record_from_authority = iteration % config.force_get_record_interval == 0;
-
-        // 处理每个域名配置
-        for domain_config in &config.domains {
-            let domain = &domain_config.domain;
-
-            // 获取IP查询URL，优先使用域名配置中的ip_url
-            let ip_url = domain_config
-                .ip_url
-                .as_ref()
-                .unwrap_or(&config.default_ip_url);
-
-            // 获取当前IP
-            match current_ip(ip_url) {
-                Ok(current_ip) => {
-                    info!("Current IP for {domain} from {ip_url}: {current_ip}");
-                    let old_ip = latest_ips.get(domain).cloned().unwrap_or_default();
-
-                    match update_record_if_need(
-                        domain_config,
-                        &config,
-                        &current_ip,
-                        &old_ip,
-                        get_current_record_from_authority,
-                    ) {
-                        Ok(result) => match result {
-                            DnsUpdateResult::Changed { old_ip } => {
-                                latest_ips.insert(domain.clone(), current_ip.clone());
-                                let result = DomainUpdateResult {
-                                    domain: domain.clone(),
-                                    new_ip: current_ip.clone(),
-                                    old_ip: old_ip.clone(),
-                                };
-
-                                send_tg(&args, &result);
-                                exec_hook_if_present(&config, domain_config, domain, result);
-                            }
-                            DnsUpdateResult::Created => {
-                                latest_ips.insert(domain.clone(), current_ip.clone());
-                                let result = DomainUpdateResult {
-                                    domain: domain.clone(),
-                                    new_ip: current_ip.clone(),
-                                    old_ip: "".to_string(),
-                                };
-
-                                send_tg(&args, &result);
-                                exec_hook_if_present(&config, domain_config, domain, result);
-                            }
-                            DnsUpdateResult::Unchanged => {}
-                        },
-                        Err(e) => {
-                            error!("Error updating domain {}: {}", domain, e);
-                        }
-                    }
+        for (domain_config, schedule) in config.domains.iter().zip(&mut schedules) {
+            let now = Instant::now();
+            if schedule.next_check > now {
+                continue;
+            }
+            // 从未强制获取以及距上次强制获取超过force_refresh_interval时，强制从provider获取记录
+            let wall_now = Local::now();
+            let force_refresh = schedule.last_force_refresh.is_none_or(|last| {
+                (wall_now - last).to_std().map_or(true, |elapsed| {
+                    elapsed >= config.force_refresh_interval(domain_config)
+                })
+            });
+            if force_refresh {
+                schedule.last_force_refresh = Some(wall_now);
+            }
+            let failure = check_domain(&args, &config, domain_config, &mut state, force_refresh);
+            let delay = schedule.record_outcome(
+                &config.retry,
+                config.interval(domain_config),
+                &domain_config.label(),
+                failure,
+            );
+            schedule.next_check = domain_config.next_check(delay);
+            state.domains.insert(domain_config.label(), schedule.save());
+            persist_state(&config, &state, &mut saved_state);
+        }
+
+        let next_check = schedules
+            .iter()
+            .map(|schedule| schedule.next_check)
+            .min()
+            .expect("at least one domain is configured");
+        let wait = next_check.saturating_duration_since(Instant::now());
+        info!("Sleeping for {} seconds...", wait.as_secs());
+        let events = wait_next_cycle(&mut wakeups, wait);
+        if events.contains(&Event::Reload) {
+            reload_config(&args, &mut config, &mut schedules, &mut state);
+        }
+        if events.contains(&Event::AddressChanged) {
+            check_on_address_change(&config.domains, &mut schedules, Local::now());
+        }
+    }
+}
+
+/// 地址变化时，当前处于时段内且未因认证失败暂停的域名立即检查
+fn check_on_address_change(
+    domains: &[DomainConfig],
+    schedules: &mut [DomainSchedule],
+    now: DateTime<Local>,
+) {
+    for (domain_config, schedule) in domains.iter().zip(schedules) {
+        if !schedule.paused && domain_config.in_windows(now) {
+            schedule.next_check = Instant::now();
+        }
+    }
+}
+
+/// history命令：按条件查询历史文件并输出到标准输出
+fn show_history(
+    config_path: &PathBuf,
+    domain: Option<&str>,
+    since: Option<DateTime<Local>>,
+    format: OutputFormat,
+) -> Result<(), Error> {
+    let config = load_config(config_path)?;
+    if config.history_file.is_empty() {
+        return Err(anyhow!("history_file is disabled in {:?}", config_path));
+    }
+    let entries = History::new(&config.history_file).query(domain, since)?;
+    let output = match format {
+        OutputFormat::Table => history::format_table(&entries),
+        OutputFormat::Json => serde_json::to_string_pretty(&entries)? + "\n",
+        OutputFormat::Csv => history::format_csv(&entries),
+    };
+    print!("{output}");
+    Ok(())
+}
+
+/// 状态有变化时写入状态文件，未配置状态文件或写入失败时只保留在内存中
+fn persist_state(config: &Config, state: &State, saved_state: &mut State) {
+    if config.state_file.is_empty() || state == saved_state {
+        return;
+    }
+    match state.save(Path::new(&config.state_file)) {
+        Ok(()) => *saved_state = state.clone(),
+        Err(e) => warn!("Failed to save state: {e}"),
+    }
+}
+
+/// 检查单个域名配置：获取当前IP，并计算每个记录应当指向的IP后同步到全部目标，失败时返回错误类型
+fn check_domain(
+    args: &Args,
+    config: &Config,
+    domain_config: &DomainConfig,
+    state: &mut State,
+    force_refresh: bool,
+) -> Option<ErrorKind> {
+    let domain = domain_config.label();
+    match config
+        .retry
+        .retry_transient(|| domain_current_ip(config, domain_config))
+        .and_then(|current_ip| Ok((domain_config.records(&current_ip)?, current_ip)))
+    {
+        Ok((records, current_ip)) => {
+            info!("Current IP for {domain}: {current_ip}");
+            for (domain, ip) in &records {
+                let old_ip = state
+                    .detected_ips
+                    .insert(record_key(domain, get_record_type(ip)), ip.clone());
+                if old_ip.as_ref() != Some(ip) {
+                    config.record_history(HistoryEntry {
+                        time: Local::now(),
+                        event: HistoryEvent::IpChange,
+                        domain: domain.clone(),
+                        provider: None,
+                        old_ip,
+                        new_ip: ip.clone(),
+                        outcome: None,
+                        error: None,
+                    });
                 }
+            }
+            update_domain(
+                args,
+                config,
+                domain_config,
+                &records,
+                &mut state.targets,
+                force_refresh,
+            )
+        }
+        Err(e) => {
+            error!("Error fetching current IP for {domain}: {e}");
+            Some(retry::classify(&e))
+        }
+    }
+}
+
+/// 在后台线程中订阅网卡地址变化，全局地址变化时通过channel通知主循环，订阅失败时返回false
+#[cfg(target_os = "linux")]
+fn watch_address_changes(sender: std::sync::mpsc::Sender<Event>) -> bool {
+    let watcher = match netlink::AddressWatcher::subscribe() {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Failed to watch address changes, falling back to polling: {e}");
+            return false;
+        }
+    };
+    std::thread::spawn(move || {
+        loop {
+            let events = match watcher.wait() {
+                Ok(events) => events,
                 Err(e) => {
-                    error!("Error fetching current IP for {domain} from {ip_url}: {e}");
+                    error!("Stop watching address changes: {e}");
+                    break;
                 }
+            };
+            for event in &events {
+                debug!("Address change: {event}");
+            }
+            // 空列表表示有事件被丢弃，同样触发更新
+            if (events.is_empty() || events.iter().any(|event| event.is_global()))
+                && sender.send(Event::AddressChanged).is_err()
+            {
+                break;
             }
         }
+    });
+    info!("Watching network address changes");
+    true
+}
+
+/// 在后台线程中接收SIGHUP，watch_file为true时同时监听配置文件的修改，触发时通知主循环重新加载配置
+#[cfg(target_os = "linux")]
+fn watch_reload(
+    config_path: &Path,
+    watch_file: bool,
+    sender: std::sync::mpsc::Sender<Event>,
+) -> bool {
+    let mut watching = false;
+    match reload::SighupListener::subscribe() {
+        Ok(listener) => {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                while listener.wait().is_ok() {
+                    info!("Received SIGHUP");
+                    if sender.send(Event::Reload).is_err() {
+                        break;
+                    }
+                }
+            });
+            watching = true;
+        }
+        Err(e) => warn!("Failed to handle SIGHUP, reload on signal is disabled: {e}"),
+    }
+    if watch_file {
+        match reload::FileWatcher::watch(config_path) {
+            Ok(watcher) => {
+                std::thread::spawn(move || {
+                    loop {
+                        if let Err(e) = watcher.wait() {
+                            error!("Stop watching configuration file: {e}");
+                            break;
+                        }
+                        info!("Configuration file changed");
+                        if sender.send(Event::Reload).is_err() {
+                            break;
+                        }
+                    }
+                });
+                info!("Watching configuration file {}", config_path.display());
+                watching = true;
+            }
+            Err(e) => warn!("Failed to watch configuration file: {e}"),
+        }
+    }
+    watching
+}
+
+/// 重新加载配置文件，新配置无效时继续使用原配置
+fn reload_config(
+    args: &Args,
+    config: &mut Config,
+    schedules: &mut Vec<DomainSchedule>,
+    state: &mut State,
+) {
+    let new_config = match load_config(&args.config) {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            error!("Failed to reload configuration, keep using the previous one: {e}");
+            return;
+        }
+    };
+    if new_config.watch_address_changes != config.watch_address_changes
+        || new_config.watch_config_file != config.watch_config_file
+    {
+        warn!("watch_address_changes and watch_config_file take effect after restart");
+    }
+    let diff = apply_config(config, new_config, schedules);
+    state.retain(
+        &config.target_keys(),
+        &config.domain_keys(),
+        &config.record_keys(),
+    );
+    info!(
+        "Reloaded configuration: {} added, {} changed, {} removed, {} unchanged",
+        diff.added, diff.changed, diff.removed, diff.unchanged
+    );
+}
+
+/// 用新配置替换当前配置：未变化的域名保留调度状态，新增和修改的域名重置状态并立即检查
+///
+/// 全局配置 (如默认token、sleep_secs、retry) 变化时，全部域名都视为修改
+fn apply_config(
+    config: &mut Config,
+    new_config: Config,
+    schedules: &mut Vec<DomainSchedule>,
+) -> ConfigDiff {
+    let globals_changed = config.global_settings() != new_config.global_settings();
+    let mut previous: Vec<(String, Option<Value>, _, Vec<Target>, DomainSchedule)> = config
+        .domains
+        .iter()
+        .zip(schedules.drain(..))
+        .map(|(domain_config, schedule)| {
+            (
+                domain_config.label(),
+                serde_json::to_value(domain_config).ok(),
+                config.schedule_settings(domain_config),
+                config.targets(domain_config),
+                schedule,
+            )
+        })
+        .collect();
+
+    let mut diff = ConfigDiff::default();
+    for domain_config in &new_config.domains {
+        let label = domain_config.label();
+        let value = serde_json::to_value(domain_config).ok();
+        let schedule = match previous.iter().position(|(old, ..)| *old == label) {
+            Some(i) => {
+                let (_, old_value, old_settings, old_targets, mut schedule) = previous.remove(i);
+                if !globals_changed && old_value == value {
+                    diff.unchanged += 1;
+                    schedule
+                } else if old_settings == new_config.schedule_settings(domain_config)
+                    && old_targets == new_config.targets(domain_config)
+                {
+                    // 调度配置和更新目标 (provider及其凭据等参数) 都未变化时保留退避和暂停，
+                    // 下一次检查强制获取记录，未退避时立即检查
+                    info!("Domain {label} changed");
+                    diff.changed += 1;
+                    schedule.last_force_refresh = None;
+                    if !schedule.paused && schedule.failures == 0 {
+                        schedule.next_check = domain_config.next_check(Duration::ZERO);
+                    }
+                    schedule
+                } else {
+                    info!("Domain {label} changed, resetting its schedule");
+                    diff.changed += 1;
+                    DomainSchedule::restore(domain_config, None)
+                }
+            }
+            None => {
+                info!("Domain {label} added");
+                diff.added += 1;
+                DomainSchedule::restore(domain_config, None)
+            }
+        };
+        schedules.push(schedule);
+    }
+    for (label, ..) in &previous {
+        info!("Domain {label} removed");
+    }
+    diff.removed = previous.len();
+    *config = new_config;
+    diff
+}
 
-        info!("Sleeping for {} seconds...", config.sleep_secs);
-        sleep(Duration::from_secs(config.sleep_secs));
-        iteration += 1;
+/// 等待下一轮更新：到达轮询间隔，或收到事件后在防抖时间内没有新的事件，返回收到的事件
+fn wait_next_cycle(wakeups: &mut Option<Receiver<Event>>, interval: Duration) -> Vec<Event> {
+    let Some(receiver) = wakeups else {
+        sleep(interval);
+        return Vec::new();
+    };
+    let deadline = Instant::now() + interval;
+    match receiver.recv_timeout(interval) {
+        Ok(event) => {
+            // 合并短时间内的多个事件，如PPPoE重连时先删除旧地址再添加新地址，编辑器保存时多次写入
+            let mut events = vec![event];
+            let settle_deadline = Instant::now() + ADDRESS_CHANGE_MAX_DELAY;
+            while Instant::now() < settle_deadline {
+                match receiver.recv_timeout(ADDRESS_CHANGE_DEBOUNCE) {
+                    Ok(event) if !events.contains(&event) => events.push(event),
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+            if events.contains(&Event::AddressChanged) {
+                info!("Network address changed, updating now");
+            }
+            events
+        }
+        Err(RecvTimeoutError::Timeout) => Vec::new(),
+        Err(RecvTimeoutError::Disconnected) => {
+            warn!("Event watchers stopped, falling back to polling");
+            *wakeups = None;
+            sleep(deadline.saturating_duration_since(Instant::now()));
+            Vec::new()
+        }
     }
 }
 
 fn exec_hook_if_present(
     config: &Config,
     domain_config: &DomainConfig,
-    domain_key: &String,
+    domain_key: &str,
     result: DomainUpdateResult,
 ) {
     // 如果IP发生变化，执行hook指令
@@ -526,51 +1597,553 @@ struct TelegramMessage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[test]
-    fn test_parse_domain() {
-        // 测试根域名
+    fn test_parse_config() {
+        let config = parse_config(
+            r#"
+default_provider = "dnspod"
+default_dnspod_token = "id,secret"
+default_powerdns_url = "http://127.0.0.1:8081"
+default_powerdns_api_key = "key"
+
+[[domains]]
+domain = "www.example.com"
+
+[[domains]]
+domain = "home.example.com"
+provider = "powerdns"
+powerdns_notify = true
+
+[[domains]]
+domain = "api.example.com"
+provider = "custom"
+[domains.custom]
+command = "/usr/local/bin/updater"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.targets(&config.domains[0])[0].provider, "dnspod");
+        let params = &config.targets(&config.domains[1])[0].params;
+        assert_eq!(params["powerdns_url"], "http://127.0.0.1:8081");
+        assert_eq!(params["powerdns_notify"], true);
+        assert!(!params.contains_key("provider"));
+
+        // 缺少必填的Provider配置
+        let err = parse_config(
+            r#"
+[[domains]]
+domain = "www.example.com"
+provider = "hetzner"
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("hetzner_token"));
+    }
+
+    #[test]
+    fn test_targets() {
+        // 多个更新目标：目标中的配置覆盖域名中的配置
+        let config = parse_config(
+            r#"
+default_provider = "dnspod"
+default_dnspod_token = "id,secret"
+
+[[domains]]
+domain = "mirror.example.com"
+cloudflare_token = "cf"
+partial_failure = "require_all"
+[[domains.targets]]
+provider = "cloudflare"
+[[domains.targets]]
+dnspod_token = "other,secret"
+"#,
+        )
+        .unwrap();
+        let mirror = &config.domains[0];
         assert_eq!(
-            parse_domain("example.com").unwrap(),
-            ("@".to_string(), "example.com".to_string())
+            mirror.partial_failure,
+            Some(PartialFailurePolicy::RequireAll)
         );
+        let targets = config.targets(mirror);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].provider, "cloudflare");
+        assert_eq!(targets[0].params["cloudflare_token"], "cf");
+        assert_eq!(targets[1].provider, "dnspod");
+        assert_eq!(targets[1].params["dnspod_token"], "other,secret");
+        assert_ne!(
+            targets[0].key(&mirror.domain, "A"),
+            targets[1].key(&mirror.domain, "A")
+        );
+        assert_ne!(
+            targets[0].key(&mirror.domain, "A"),
+            targets[0].key(&mirror.domain, "AAAA")
+        );
+    }
+
+    #[test]
+    fn test_batching() {
+        // 同一zone下的多个记录名称
+        let config = parse_config(
+            r#"
+[[domains]]
+names = ["@", "www", "vpn"]
+zone = "example.com"
+cloudflare_token = "cf"
+"#,
+        )
+        .unwrap();
         assert_eq!(
-            parse_domain("@.example.com").unwrap(),
-            ("@".to_string(), "example.com".to_string())
+            config.domains[0].domains(),
+            ["example.com", "www.example.com", "vpn.example.com"]
         );
+        assert_eq!(config.domains[0].label(), "example.com [@, www, vpn]");
+        let err = parse_config(
+            r#"
+[[domains]]
+names = ["www"]
+cloudflare_token = "cf"
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("zone is required"));
+    }
 
-        // 测试单级子域名
+    #[test]
+    fn test_prefix_mode() {
+        // IPv6前缀模式：当前前缀与各记录的接口标识组合
+        let dir = std::env::temp_dir().join(format!("ddns-prefix-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let hosts = dir.join("hosts");
+        fs::write(&hosts, "1.2.3.4\tnas.example.com\n").unwrap();
+        let content = format!(
+            r#"
+[[domains]]
+zone = "example.com"
+provider = "file"
+file_path = {hosts:?}
+ipv6_prefix = {{ length = 56 }}
+[domains.interface_ids]
+nas = "::1234:5678"
+printer = "::1:0:0:0:abcd"
+"#
+        );
+        let config = parse_config(&content).unwrap();
+        let records = config.domains[0].records("2001:db8:0:ff00::1").unwrap();
         assert_eq!(
-            parse_domain("www.example.com").unwrap(),
-            ("www".to_string(), "example.com".to_string())
+            records,
+            [
+                (
+                    "nas.example.com".to_string(),
+                    "2001:db8:0:ff00::1234:5678".to_string()
+                ),
+                (
+                    "printer.example.com".to_string(),
+                    "2001:db8:0:ff01::abcd".to_string()
+                ),
+            ]
         );
+        assert!(config.domains[0].records("1.2.3.4").is_err());
+
+        // 只更新AAAA记录，同名的A记录保留
+        let target = &config.targets(&config.domains[0])[0];
+        for (domain, ip) in &records {
+            let results = update_target(std::slice::from_ref(domain), target, ip, &config.retry);
+            assert!(matches!(results[..], [Ok(DnsUpdateResult::Created)]));
+        }
         assert_eq!(
-            parse_domain("blog.example.com").unwrap(),
-            ("blog".to_string(), "example.com".to_string())
+            fs::read_to_string(&hosts).unwrap(),
+            "1.2.3.4\tnas.example.com\n\
+             2001:db8:0:ff00::1234:5678\tnas.example.com\n\
+             2001:db8:0:ff01::abcd\tprinter.example.com\n"
         );
+        fs::remove_dir_all(&dir).unwrap();
+
+        // 不按地址族选择记录的provider不能使用前缀模式
+        let err = parse_config(
+            r#"
+[[domains]]
+zone = "example.com"
+cloudflare_token = "cf"
+ipv6_prefix = { length = 56 }
+[domains.interface_ids]
+nas = "::1234:5678"
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("does not support ipv6_prefix"));
+    }
+
+    #[test]
+    fn test_ip_source() {
+        // IP来源：域名的ip_source优先，其次是ip_url和default_ip_source
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+default_ip_source = { upnp = {} }
+
+[[domains]]
+domain = "a.example.com"
+
+[[domains]]
+domain = "b.example.com"
+ip_url = "https://api.ipify.org"
+
+[[domains]]
+domain = "c.example.com"
+ip_source = { natpmp = { gateway = "192.168.1.1" } }
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.ip_source(&config.domains[0]),
+            IpSource::Upnp(_)
+        ));
+        assert!(matches!(
+            config.ip_source(&config.domains[1]),
+            IpSource::Http(url) if url == "https://api.ipify.org"
+        ));
+        match config.ip_source(&config.domains[2]) {
+            IpSource::NatPmp(natpmp) => {
+                assert_eq!(natpmp.gateway, Some("192.168.1.1".parse().unwrap()));
+                assert_eq!(natpmp.port, 5351);
+            }
+            source => panic!("unexpected ip source {source}"),
+        }
+
+        // 命令和文件来源，以及IP来源的校验
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+ip_source = { command = "cat /run/wan_ip" }
 
-        // 测试多级子域名
+[[domains]]
+domain = "b.example.com"
+ip_source = { file = "/run/wan_ip" }
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.ip_source(&config.domains[0]),
+            IpSource::Command(command) if command == "cat /run/wan_ip"
+        ));
+        assert!(matches!(
+            config.ip_source(&config.domains[1]),
+            IpSource::File(path) if path.to_str() == Some("/run/wan_ip")
+        ));
+        let err = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+ip_url = "whatismyip.akamai.com"
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid ip source"));
+    }
+
+    #[test]
+    fn test_bind() {
+        // 多WAN口：每个域名通过不同的网卡获取IP，绑定字段不会被当作provider配置
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "wan1.example.com"
+bind_address = "192.0.2.10"
+
+[[domains]]
+domain = "wan2.example.com"
+bind_address = "198.51.100.10"
+bind_providers = true
+"#,
+        )
+        .unwrap();
         assert_eq!(
-            parse_domain("api.v2.example.com").unwrap(),
-            ("api.v2".to_string(), "example.com".to_string())
+            config.domains[0].bind().address,
+            Some("192.0.2.10".parse().unwrap())
+        );
+        assert!(!config.domains[0].bind_providers);
+        assert!(config.domains[1].bind_providers);
+        assert!(
+            !config.domains[1]
+                .provider_params
+                .contains_key("bind_address")
         );
+        let err = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+bind_providers = true
+"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("bind_providers requires"));
+    }
+
+    #[test]
+    fn test_schedule() {
+        // 每个域名的检查间隔、强制获取记录的间隔和检查时段
+        let config = parse_config(
+            r#"
+sleep_secs = 60
+force_get_record_interval = 10
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+
+[[domains]]
+domain = "b.example.com"
+interval = "30s"
+force_refresh_interval = 3600
+windows = ["* 8-23 * * *", "0 */2 * * *"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.interval(&config.domains[0]), Duration::from_secs(60));
         assert_eq!(
-            parse_domain("deep.nested.sub.example.com").unwrap(),
-            ("deep.nested.sub".to_string(), "example.com".to_string())
+            config.force_refresh_interval(&config.domains[0]),
+            Duration::from_secs(600)
         );
+        assert_eq!(config.interval(&config.domains[1]), Duration::from_secs(30));
+        assert_eq!(
+            config.force_refresh_interval(&config.domains[1]),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(config.domains[1].windows.len(), 2);
+        for (invalid, message) in [
+            (r#"interval = "5x""#, "invalid duration"),
+            ("interval = 0", "interval must be greater than 0"),
+            (r#"windows = ["* 24 * * *"]"#, "invalid window"),
+            (r#"windows = ["0 0 30 2 *"]"#, "never match"),
+        ] {
+            let err = parse_config(&format!(
+                "default_cloudflare_token = \"cf\"\n[[domains]]\ndomain = \"a.example.com\"\n{invalid}\n"
+            ))
+            .unwrap_err();
+            assert!(err.to_string().contains(message), "{err}");
+        }
+
+        // 地址变化时只立即检查处于时段内且未暂停的域名
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+
+[[domains]]
+domain = "b.example.com"
+windows = ["* 8-22 * * *"]
+
+[[domains]]
+domain = "c.example.com"
+"#,
+        )
+        .unwrap();
+        let later = Instant::now() + Duration::from_secs(3600);
+        let mut schedules: Vec<DomainSchedule> = config
+            .domains
+            .iter()
+            .map(|domain_config| DomainSchedule {
+                next_check: later,
+                ..DomainSchedule::restore(domain_config, None)
+            })
+            .collect();
+        schedules[2].paused = true;
+        let night = Local.with_ymd_and_hms(2024, 6, 1, 23, 30, 0).unwrap();
+        check_on_address_change(&config.domains, &mut schedules, night);
+        assert!(schedules[0].next_check <= Instant::now());
+        assert_eq!(schedules[1].next_check, later);
+        assert_eq!(schedules[2].next_check, later);
+        let morning = Local.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+        check_on_address_change(&config.domains, &mut schedules, morning);
+        assert!(schedules[1].next_check <= Instant::now());
+        assert_eq!(schedules[2].next_check, later);
+    }
+
+    #[test]
+    fn test_retry() {
+        // 连续认证失败达到阈值后暂停，成功后恢复
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[retry]
+max_backoff = "10m"
+auth_failure_threshold = 2
+auth_failure_pause = "2h"
+
+[[domains]]
+domain = "a.example.com"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.retry.transient_retries, 2);
+        assert_eq!(config.retry.max_backoff, Duration::from_secs(600));
+        let mut schedule = DomainSchedule {
+            next_check: Instant::now(),
+            last_force_refresh: None,
+            failures: 0,
+            auth_failures: 0,
+            paused: false,
+        };
+        let interval = Duration::from_secs(120);
+        let delay = schedule.record_outcome(&config.retry, interval, "a", Some(ErrorKind::Auth));
+        assert!(delay >= interval && delay <= Duration::from_secs(600));
+        assert!(!schedule.paused);
+        let delay = schedule.record_outcome(&config.retry, interval, "a", Some(ErrorKind::Auth));
+        assert_eq!(delay, Duration::from_secs(7200));
+        assert!(schedule.paused);
         assert_eq!(
-            parse_domain("a.b.c.d.example.com").unwrap(),
-            ("a.b.c.d".to_string(), "example.com".to_string())
+            schedule.record_outcome(&config.retry, interval, "a", None),
+            interval
         );
+        assert!(!schedule.paused && schedule.failures == 0);
+    }
 
-        // 测试错误情况
-        assert!(parse_domain("invalid").is_err());
-        assert!(parse_domain("").is_err());
+    #[test]
+    fn test_state() {
+        // 重启后从状态文件恢复退避中的调度
+        let config = parse_config(
+            r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.state_file, "ddns_state.json");
+        assert_eq!(
+            config.target_keys(),
+            [
+                "a.example.com/A/cloudflare#0",
+                "a.example.com/AAAA/cloudflare#0"
+            ]
+        );
+        assert_eq!(
+            config.record_keys(),
+            ["a.example.com/A", "a.example.com/AAAA"]
+        );
+        let mut schedule = DomainSchedule::restore(&config.domains[0], None);
+        assert!(schedule.next_check <= Instant::now());
+        let interval = Duration::from_secs(120);
+        schedule.record_outcome(&config.retry, interval, "a", Some(ErrorKind::Other));
+        schedule.next_check = Instant::now() + Duration::from_secs(300);
+        let saved = schedule.save();
+        assert_eq!(saved.failures, 1);
+        let restored = DomainSchedule::restore(&config.domains[0], Some(&saved));
+        assert!(restored.next_check > Instant::now() + Duration::from_secs(290));
+        assert_eq!(restored.failures, 1);
+    }
 
-        // 测试特殊情况
+    #[test]
+    fn test_reload() {
+        // 重新加载配置：未变化的域名保留调度状态，新增和调度配置变化的域名立即检查
+        let reloaded = |extra: &str| {
+            parse_config(&format!(
+                r#"
+default_cloudflare_token = "cf"
+
+[[domains]]
+domain = "a.example.com"
+{extra}
+"#
+            ))
+            .unwrap()
+        };
+        let mut config = reloaded("");
+        let mut schedule = DomainSchedule::restore(&config.domains[0], None);
+        schedule.record_outcome(
+            &config.retry,
+            Duration::from_secs(120),
+            "a",
+            Some(ErrorKind::Other),
+        );
+        let mut schedules = vec![schedule];
+        let diff = apply_config(
+            &mut config,
+            reloaded("\n[[domains]]\ndomain = \"b.example.com\""),
+            &mut schedules,
+        );
         assert_eq!(
-            parse_domain("test.co.uk").unwrap(),
-            ("test".to_string(), "co.uk".to_string())
+            diff,
+            ConfigDiff {
+                added: 1,
+                unchanged: 1,
+                ..ConfigDiff::default()
+            }
+        );
+        assert_eq!(schedules[0].failures, 1);
+        assert!(schedules[1].next_check <= Instant::now());
+
+        // 调度配置和更新目标以外的修改保留暂停状态，下一次检查时强制获取记录
+        schedules[0].paused = true;
+        schedules[0].auth_failures = 3;
+        schedules[0].next_check = Instant::now() + Duration::from_secs(7200);
+        schedules[0].last_force_refresh = Some(Local::now());
+        let diff = apply_config(
+            &mut config,
+            reloaded("hook_command = \"true\""),
+            &mut schedules,
+        );
+        assert_eq!(
+            diff,
+            ConfigDiff {
+                changed: 1,
+                removed: 1,
+                ..ConfigDiff::default()
+            }
+        );
+        assert_eq!(schedules.len(), 1);
+        assert!(schedules[0].paused);
+        assert_eq!(schedules[0].failures, 1);
+        assert_eq!(schedules[0].auth_failures, 3);
+        assert!(schedules[0].next_check > Instant::now() + Duration::from_secs(7000));
+        assert!(schedules[0].last_force_refresh.is_none());
+
+        // 更换token后解除暂停并立即检查
+        let diff = apply_config(
+            &mut config,
+            reloaded("hook_command = \"true\"\ncloudflare_token = \"new\""),
+            &mut schedules,
+        );
+        assert_eq!(
+            diff,
+            ConfigDiff {
+                changed: 1,
+                ..ConfigDiff::default()
+            }
+        );
+        assert!(!schedules[0].paused);
+        assert_eq!(schedules[0].failures, 0);
+        assert_eq!(schedules[0].auth_failures, 0);
+        assert!(schedules[0].next_check <= Instant::now());
+
+        // 调度配置变化时重置
+        schedules[0].paused = true;
+        schedules[0].failures = 1;
+        let diff = apply_config(&mut config, reloaded("interval = \"1m\""), &mut schedules);
+        assert_eq!(
+            diff,
+            ConfigDiff {
+                changed: 1,
+                ..ConfigDiff::default()
+            }
         );
+        assert!(!schedules[0].paused);
+        assert_eq!(schedules[0].failures, 0);
+        assert_eq!(config.domains[0].interval, Some(Duration::from_secs(60)));
     }
 }
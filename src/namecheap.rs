@@ -0,0 +1,540 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+
+use crate::registry::{BatchContext, ProviderConfig, ProviderContext};
+use crate::{
+    BatchDnsProvider, DnsProvider, DnsRecord, DnsUpdateResult, get_record_type, relative_name,
+};
+
+const NAMECHEAP_API: &str = "https://api.namecheap.com/xml.response";
+
+/// getList 每页的域名数量 (最大为100)
+const DOMAIN_PAGE_SIZE: usize = 100;
+
+// ========== Namecheap 相关结构 ==========
+
+/// XML响应中的一个元素及其属性
+#[derive(Debug)]
+struct XmlElement {
+    name: String,
+    attributes: HashMap<String, String>,
+}
+
+/// 解析后的Namecheap响应
+#[derive(Debug)]
+struct NamecheapResponse {
+    elements: Vec<XmlElement>,
+}
+
+impl NamecheapResponse {
+    /// 解析XML，ApiResponse的Status不为OK时返回其中的错误信息
+    fn parse(xml: &str) -> Result<Self, Error> {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut elements = Vec::new();
+        let mut errors = Vec::new();
+        let mut in_error = false;
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) | Event::Empty(e) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    in_error = name == "Error";
+                    let mut attributes = HashMap::new();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        attributes.insert(
+                            String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+                            attr.unescape_value()?.into_owned(),
+                        );
+                    }
+                    elements.push(XmlElement { name, attributes });
+                }
+                Event::Text(text) if in_error => {
+                    errors.push(text.decode()?.into_owned());
+                }
+                Event::End(_) => in_error = false,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        let response = NamecheapResponse { elements };
+        match response.attribute("ApiResponse", "Status") {
+            Some("OK") => Ok(response),
+            Some(_) => Err(anyhow!("Namecheap API error: {}", errors.join("; "))),
+            None => Err(anyhow!("Invalid Namecheap API response: {}", xml)),
+        }
+    }
+
+    fn find(&self, name: &str) -> impl Iterator<Item = &XmlElement> {
+        self.elements.iter().filter(move |e| e.name == name)
+    }
+
+    fn attribute(&self, element: &str, attribute: &str) -> Option<&str> {
+        self.find(element)
+            .next()
+            .and_then(|e| e.attributes.get(attribute))
+            .map(String::as_str)
+    }
+}
+
+/// getHosts 返回的主机记录，setHosts 时需要原样回写
+#[derive(Debug, Clone)]
+struct NamecheapHost {
+    id: String,
+    name: String,
+    record_type: String,
+    address: String,
+    mx_pref: String,
+    ttl: String,
+}
+
+// ========== Namecheap Provider 实现 ==========
+
+pub struct NamecheapProvider {
+    api: String,
+    api_user: String,
+    api_key: String,
+    client_ip: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl NamecheapProvider {
+    /// client_ip 为调用方的IP，需要在Namecheap后台加入API白名单
+    pub fn new(api_user: String, api_key: String, client_ip: String, record_name: String) -> Self {
+        NamecheapProvider {
+            api: NAMECHEAP_API.to_string(),
+            api_user,
+            api_key,
+            client_ip,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// 在主机记录列表中查找和写入的类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 调用Namecheap API，command为如 "namecheap.domains.dns.getHosts" 的命令名
+    fn call(&self, command: &str, params: &[(String, String)]) -> Result<NamecheapResponse, Error> {
+        let mut form: Vec<(String, String)> = vec![
+            ("ApiUser".to_string(), self.api_user.clone()),
+            ("ApiKey".to_string(), self.api_key.clone()),
+            ("UserName".to_string(), self.api_user.clone()),
+            ("ClientIp".to_string(), self.client_ip.clone()),
+            ("Command".to_string(), command.to_string()),
+        ];
+        form.extend_from_slice(params);
+
+        // setHosts 的参数可能很长，统一使用POST表单提交
+        let client = crate::http_client()?;
+        let text = client.post(&self.api).form(&form).send()?.text()?;
+        NamecheapResponse::parse(&text)
+    }
+
+    /// 分页获取账户下的全部域名
+    fn list_domains(&self) -> Result<Vec<String>, Error> {
+        let mut domains = Vec::new();
+        for page in 1.. {
+            let response = self.call(
+                "namecheap.domains.getList",
+                &[
+                    ("PageSize".to_string(), DOMAIN_PAGE_SIZE.to_string()),
+                    ("Page".to_string(), page.to_string()),
+                ],
+            )?;
+            let count = domains.len();
+            domains.extend(
+                response
+                    .find("Domain")
+                    .filter_map(|e| e.attributes.get("Name"))
+                    .cloned(),
+            );
+            // 不满一页即为最后一页
+            if domains.len() - count < DOMAIN_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(domains)
+    }
+
+    /// 获取(SLD, TLD, 相对记录名)
+    /// 例如: "www.example.co.uk" -> ("example", "co.uk", "www")
+    fn get_zone(&self) -> Result<(String, String, String), Error> {
+        let domains = self.list_domains()?;
+        let (domain, name) = domains
+            .iter()
+            .filter_map(|domain| relative_name(&self.record_name, domain).map(|n| (domain, n)))
+            .max_by_key(|(domain, _)| domain.len())
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))?;
+        let (sld, tld) = domain
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Invalid domain: {}", domain))?;
+        Ok((sld.to_string(), tld.to_string(), name))
+    }
+
+    /// 获取域名下的全部主机记录以及EmailType
+    fn get_hosts(
+        &self,
+        sld: &str,
+        tld: &str,
+    ) -> Result<(Vec<NamecheapHost>, Option<String>), Error> {
+        let response = self.call(
+            "namecheap.domains.dns.getHosts",
+            &[
+                ("SLD".to_string(), sld.to_string()),
+                ("TLD".to_string(), tld.to_string()),
+            ],
+        )?;
+        if response.attribute("DomainDNSGetHostsResult", "IsUsingOurDNS") != Some("true") {
+            return Err(anyhow!(
+                "Domain {}.{} is not using Namecheap DNS, refusing to manage its hosts",
+                sld,
+                tld
+            ));
+        }
+        let email_type = response
+            .attribute("DomainDNSGetHostsResult", "EmailType")
+            .map(str::to_string);
+
+        let mut hosts = Vec::new();
+        for element in response.find("host") {
+            let attr = |name: &str| {
+                element.attributes.get(name).cloned().ok_or_else(|| {
+                    anyhow!("Namecheap host entry is missing {}: {:?}", name, element)
+                })
+            };
+            hosts.push(NamecheapHost {
+                id: attr("HostId")?,
+                name: attr("Name")?,
+                record_type: attr("Type")?,
+                address: attr("Address")?,
+                mx_pref: attr("MXPref").unwrap_or_else(|_| "10".to_string()),
+                ttl: attr("TTL").unwrap_or_else(|_| "1800".to_string()),
+            });
+        }
+        Ok((hosts, email_type))
+    }
+
+    /// 用完整的主机列表覆盖域名的DNS记录
+    fn set_hosts(
+        &self,
+        sld: &str,
+        tld: &str,
+        hosts: &[NamecheapHost],
+        email_type: Option<String>,
+    ) -> Result<(), Error> {
+        let mut params = vec![
+            ("SLD".to_string(), sld.to_string()),
+            ("TLD".to_string(), tld.to_string()),
+        ];
+        if let Some(email_type) = email_type {
+            params.push(("EmailType".to_string(), email_type));
+        }
+        for (i, host) in hosts.iter().enumerate() {
+            let n = i + 1;
+            params.push((format!("HostName{n}"), host.name.clone()));
+            params.push((format!("RecordType{n}"), host.record_type.clone()));
+            params.push((format!("Address{n}"), host.address.clone()));
+            params.push((format!("MXPref{n}"), host.mx_pref.clone()));
+            params.push((format!("TTL{n}"), host.ttl.clone()));
+        }
+
+        let response = self.call("namecheap.domains.dns.setHosts", &params)?;
+        if response.attribute("DomainDNSSetHostsResult", "IsSuccess") != Some("true") {
+            return Err(anyhow!("Namecheap setHosts did not report success"));
+        }
+        debug!("namecheap setHosts result: success, {} hosts", hosts.len());
+        Ok(())
+    }
+
+    /// 新增的主机记录
+    fn new_host(name: String, current_ip: &str) -> NamecheapHost {
+        NamecheapHost {
+            id: String::new(),
+            name,
+            record_type: get_record_type(current_ip).to_string(),
+            address: current_ip.to_string(),
+            mx_pref: "10".to_string(),
+            ttl: "300".to_string(),
+        }
+    }
+}
+
+impl DnsProvider for NamecheapProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (sld, tld, name) = self.get_zone()?;
+        let (hosts, _) = self.get_hosts(&sld, &tld)?;
+        let host = hosts.into_iter().find(|host| {
+            host.name.eq_ignore_ascii_case(&name) && host.record_type == self.record_type
+        });
+        match host {
+            Some(host) => {
+                info!("current namecheap host is {:?}", host);
+                Ok(Some(DnsRecord {
+                    id: host.id,
+                    name: host.name,
+                    value: host.address,
+                    record_type: host.record_type,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 修改DNS记录：重新获取完整主机列表，只替换目标记录后整体回写
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let (sld, tld, _) = self.get_zone()?;
+        let (mut hosts, email_type) = self.get_hosts(&sld, &tld)?;
+        let host = hosts
+            .iter_mut()
+            .find(|host| host.id == record.id)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Namecheap host {} ({}) disappeared before update",
+                    record.name,
+                    record.id
+                )
+            })?;
+        host.address = current_ip.to_string();
+        self.set_hosts(&sld, &tld, &hosts, email_type)
+    }
+
+    /// 添加DNS记录：在完整主机列表末尾追加后整体回写
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let (sld, tld, name) = self.get_zone()?;
+        let (mut hosts, email_type) = self.get_hosts(&sld, &tld)?;
+        hosts.push(Self::new_host(name, current_ip));
+        self.set_hosts(&sld, &tld, &hosts, email_type)
+    }
+}
+
+/// 批量更新同一域名下的多个主机记录：一次getHosts，全部修改合并为一次setHosts
+pub struct NamecheapBatchProvider {
+    provider: NamecheapProvider,
+    record_names: Vec<String>,
+}
+
+impl NamecheapBatchProvider {
+    /// provider 用于访问API和确定域名，record_names 需属于同一域名
+    pub fn new(provider: NamecheapProvider, record_names: Vec<String>) -> Self {
+        NamecheapBatchProvider {
+            provider,
+            record_names,
+        }
+    }
+}
+
+impl BatchDnsProvider for NamecheapBatchProvider {
+    fn update_dns_records(&self, current_ip: &str) -> Result<Vec<DnsUpdateResult>, Error> {
+        let (sld, tld, _) = self.provider.get_zone()?;
+        let zone = format!("{sld}.{tld}");
+        let (mut hosts, email_type) = self.provider.get_hosts(&sld, &tld)?;
+        let record_type = get_record_type(current_ip);
+
+        let mut results = Vec::new();
+        for record_name in &self.record_names {
+            let name = relative_name(record_name, &zone)
+                .ok_or_else(|| anyhow!("{} does not belong to {}", record_name, zone))?;
+            let host = hosts.iter_mut().find(|host| {
+                host.name.eq_ignore_ascii_case(&name) && host.record_type == record_type
+            });
+            let result = match host {
+                Some(host) if host.address == current_ip => {
+                    DnsUpdateResult::Unchanged { record_id: None }
+                }
+                Some(host) => {
+                    let old_ip = std::mem::replace(&mut host.address, current_ip.to_string());
+                    DnsUpdateResult::Changed {
+                        old_ip,
+                        record_id: None,
+                    }
+                }
+                None => {
+                    hosts.push(NamecheapProvider::new_host(name, current_ip));
+                    DnsUpdateResult::Created
+                }
+            };
+            results.push(result);
+        }
+
+        if results
+            .iter()
+            .any(|result| !matches!(result, DnsUpdateResult::Unchanged { .. }))
+        {
+            self.provider.set_hosts(&sld, &tld, &hosts, email_type)?;
+        }
+        Ok(results)
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Namecheap配置
+#[derive(Deserialize, Debug)]
+pub struct NamecheapConfig {
+    /// Namecheap API用户名
+    pub namecheap_api_user: String,
+    /// Namecheap API Key
+    pub namecheap_api_key: String,
+    /// 调用API时上报的客户端IP，需在API白名单中，未设置时使用当前检测到的IPv4地址，更新AAAA记录时必须设置
+    #[serde(default)]
+    pub namecheap_client_ip: Option<String>,
+}
+
+impl NamecheapConfig {
+    fn provider(self, record_name: String, current_ip: &str) -> Result<NamecheapProvider, Error> {
+        let client_ip = match self.namecheap_client_ip {
+            Some(client_ip) => client_ip,
+            // Namecheap的API白名单只支持IPv4
+            None if current_ip.parse::<Ipv4Addr>().is_ok() => current_ip.to_string(),
+            None => {
+                return Err(anyhow!(
+                    "namecheap_client_ip is required to update {} with {}",
+                    record_name,
+                    current_ip
+                ));
+            }
+        };
+        Ok(NamecheapProvider::new(
+            self.namecheap_api_user,
+            self.namecheap_api_key,
+            client_ip,
+            record_name,
+        )
+        .record_type(get_record_type(current_ip)))
+    }
+}
+
+impl ProviderConfig for NamecheapConfig {
+    const NAME: &'static str = "namecheap";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(self.provider(
+            context.domain.to_string(),
+            context.current_ip,
+        )?))
+    }
+
+    fn build_batch(
+        self,
+        context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error> {
+        let first = context
+            .domains
+            .first()
+            .ok_or_else(|| anyhow!("no domains to update"))?;
+        let provider = self.provider(first.clone(), context.current_ip)?;
+        Ok(Some(Box::new(NamecheapBatchProvider::new(
+            provider,
+            context.domains.to_vec(),
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_parse_get_hosts() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<ApiResponse Status="OK" xmlns="http://api.namecheap.com/xml.response">
+  <Errors />
+  <RequestedCommand>namecheap.domains.dns.getHosts</RequestedCommand>
+  <CommandResponse Type="namecheap.domains.dns.getHosts">
+    <DomainDNSGetHostsResult Domain="example.com" EmailType="MX" IsUsingOurDNS="true">
+      <host HostId="12" Name="@" Type="A" Address="1.2.3.4" MXPref="10" TTL="1800" />
+      <host HostId="14" Name="www" Type="CNAME" Address="example.com." MXPref="10" TTL="1800" />
+    </DomainDNSGetHostsResult>
+  </CommandResponse>
+</ApiResponse>"#;
+        let response = NamecheapResponse::parse(xml).unwrap();
+        assert_eq!(
+            response.attribute("DomainDNSGetHostsResult", "EmailType"),
+            Some("MX")
+        );
+        let hosts: Vec<_> = response.find("host").collect();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[1].attributes["Address"], "example.com.");
+
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<ApiResponse Status="ERROR" xmlns="http://api.namecheap.com/xml.response">
+  <Errors><Error Number="1011150">Parameter RequestIP is invalid</Error></Errors>
+</ApiResponse>"#;
+        let err = NamecheapResponse::parse(xml).unwrap_err();
+        assert!(err.to_string().contains("Parameter RequestIP is invalid"));
+    }
+
+    #[test]
+    fn test_batch_update() {
+        const HOSTS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ApiResponse Status="OK" xmlns="http://api.namecheap.com/xml.response">
+  <CommandResponse Type="namecheap.domains.dns.getHosts">
+    <DomainDNSGetHostsResult Domain="example.com" IsUsingOurDNS="true">
+      <host HostId="12" Name="home" Type="A" Address="1.2.3.4" MXPref="10" TTL="1800" />
+      <host HostId="13" Name="home" Type="AAAA" Address="2001:db8::1" MXPref="10" TTL="1800" />
+    </DomainDNSGetHostsResult>
+  </CommandResponse>
+</ApiResponse>"#;
+        // 第一页为满页的其他域名，example.com在第二页
+        let first_page: String = (0..DOMAIN_PAGE_SIZE)
+            .map(|i| format!(r#"<Domain Name="example{i}.net" />"#))
+            .collect();
+        let first_page = format!(r#"<ApiResponse Status="OK">{first_page}</ApiResponse>"#);
+        let (address, handle) = serve_http(vec![
+            first_page,
+            r#"<ApiResponse Status="OK"><Domain Name="example.com" /></ApiResponse>"#.to_string(),
+            HOSTS.to_string(),
+            r#"<ApiResponse Status="OK"><DomainDNSSetHostsResult IsSuccess="true" /></ApiResponse>"#
+                .to_string(),
+        ]);
+        let config = NamecheapConfig {
+            namecheap_api_user: "user".to_string(),
+            namecheap_api_key: "key".to_string(),
+            namecheap_client_ip: Some("192.0.2.1".to_string()),
+        };
+        let mut provider = config
+            .provider("home.example.com".to_string(), "2001:db8::2")
+            .unwrap();
+        provider.api = format!("http://{address}");
+        let batch = NamecheapBatchProvider::new(provider, vec!["home.example.com".to_string()]);
+
+        // 只修改AAAA记录，A记录原样回写
+        let results = batch.update_dns_records("2001:db8::2").unwrap();
+        assert!(matches!(
+            &results[0],
+            DnsUpdateResult::Changed { old_ip, .. } if old_ip == "2001:db8::1"
+        ));
+        let requests = handle.join().unwrap();
+        assert!(requests[1].1.contains("Page=2"));
+        let set_hosts = &requests[3].1;
+        assert!(set_hosts.contains("RecordType1=A&Address1=1.2.3.4"));
+        assert!(set_hosts.contains("RecordType2=AAAA&Address2=2001%3Adb8%3A%3A2"));
+
+        // 更新AAAA记录时必须设置客户端IP
+        let config = NamecheapConfig {
+            namecheap_api_user: "user".to_string(),
+            namecheap_api_key: "key".to_string(),
+            namecheap_client_ip: None,
+        };
+        assert!(
+            config
+                .provider("home.example.com".to_string(), "2001:db8::2")
+                .is_err()
+        );
+    }
+}
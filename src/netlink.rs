@@ -0,0 +1,277 @@
+use anyhow::{Error, anyhow};
+use log::warn;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+// ========== rtnetlink 地址变化事件 ==========
+
+/// ifaddrmsg 之后的属性类型
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+
+/// nlmsghdr、ifaddrmsg 和 rtattr 头部的长度
+const NLMSG_HEADER_LEN: usize = 16;
+const IFADDRMSG_LEN: usize = 8;
+const RTATTR_HEADER_LEN: usize = 4;
+
+/// 地址变化的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressChange {
+    /// RTM_NEWADDR：添加或更新了地址
+    New,
+    /// RTM_DELADDR：删除了地址
+    Del,
+}
+
+/// 网卡地址变化事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressEvent {
+    pub change: AddressChange,
+    /// 网卡序号
+    pub interface_index: u32,
+    /// 变化的地址，点对点网卡 (如PPPoE) 上为本端地址
+    pub address: Option<IpAddr>,
+    /// 地址的作用域，0为全局地址，253为链路本地地址，254为本机地址
+    pub scope: u8,
+}
+
+impl AddressEvent {
+    /// 是否为全局作用域的地址，链路本地地址和本机地址的变化不影响公网IP
+    pub fn is_global(&self) -> bool {
+        self.scope == libc::RT_SCOPE_UNIVERSE
+    }
+}
+
+impl fmt::Display for AddressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match self.change {
+            AddressChange::New => "added",
+            AddressChange::Del => "removed",
+        };
+        let address = self
+            .address
+            .map_or_else(|| "address".to_string(), |ip| ip.to_string());
+        match interface_name(self.interface_index) {
+            Some(name) => write!(f, "{address} {change} on {name}"),
+            None => write!(
+                f,
+                "{address} {change} on interface #{}",
+                self.interface_index
+            ),
+        }
+    }
+}
+
+/// 订阅了IPv4和IPv6地址变化组播的rtnetlink socket
+pub struct AddressWatcher {
+    fd: OwnedFd,
+}
+
+impl AddressWatcher {
+    /// 创建socket并订阅 RTMGRP_IPV4_IFADDR 和 RTMGRP_IPV6_IFADDR
+    pub fn subscribe() -> Result<Self, Error> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(anyhow!(
+                "failed to create netlink socket: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = (libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+        let result = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(anyhow!(
+                "failed to subscribe to address changes: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        Ok(AddressWatcher { fd })
+    }
+
+    /// 阻塞等待下一批地址变化事件
+    ///
+    /// 接收缓冲区溢出时内核会丢弃事件，此时返回空列表，调用方应当视为地址可能已经变化
+    pub fn wait(&self) -> Result<Vec<AddressEvent>, Error> {
+        let mut buf = vec![0u8; 32 * 1024];
+        loop {
+            let len = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            };
+            if len >= 0 {
+                return Ok(parse_messages(&buf[..len as usize]));
+            }
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::ENOBUFS) => {
+                    warn!("netlink receive buffer overflowed, some address changes were lost");
+                    return Ok(Vec::new());
+                }
+                _ => return Err(anyhow!("failed to receive netlink message: {e}")),
+            }
+        }
+    }
+}
+
+/// 解析一次recv收到的全部消息，忽略地址变化以外的消息
+fn parse_messages(buf: &[u8]) -> Vec<AddressEvent> {
+    let mut events = Vec::new();
+    let mut rest = buf;
+    while rest.len() >= NLMSG_HEADER_LEN {
+        let len = u32::from_ne_bytes(rest[0..4].try_into().unwrap()) as usize;
+        if len < NLMSG_HEADER_LEN || len > rest.len() {
+            break;
+        }
+        let message_type = u16::from_ne_bytes(rest[4..6].try_into().unwrap());
+        let change = match message_type {
+            libc::RTM_NEWADDR => Some(AddressChange::New),
+            libc::RTM_DELADDR => Some(AddressChange::Del),
+            _ => None,
+        };
+        if let Some(change) = change
+            && let Some(event) = parse_address(change, &rest[NLMSG_HEADER_LEN..len])
+        {
+            events.push(event);
+        }
+        // 消息按4字节对齐
+        rest = rest.get(len.div_ceil(4) * 4..).unwrap_or_default();
+    }
+    events
+}
+
+/// 解析 ifaddrmsg: family(1) prefixlen(1) flags(1) scope(1) index(4)，之后为若干rtattr
+fn parse_address(change: AddressChange, payload: &[u8]) -> Option<AddressEvent> {
+    let header = payload.get(..IFADDRMSG_LEN)?;
+    let family = i32::from(header[0]);
+    let scope = header[3];
+    let interface_index = u32::from_ne_bytes(header[4..8].try_into().ok()?);
+
+    let mut address = None;
+    let mut local = None;
+    let mut rest = &payload[IFADDRMSG_LEN..];
+    while rest.len() >= RTATTR_HEADER_LEN {
+        let len = u16::from_ne_bytes([rest[0], rest[1]]) as usize;
+        let attr_type = u16::from_ne_bytes([rest[2], rest[3]]);
+        let value = rest.get(RTATTR_HEADER_LEN..len)?;
+        let ip = match (family, value.len()) {
+            (libc::AF_INET, 4) => {
+                Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(value).ok()?)))
+            }
+            (libc::AF_INET6, 16) => Some(IpAddr::V6(Ipv6Addr::from(
+                <[u8; 16]>::try_from(value).ok()?,
+            ))),
+            _ => None,
+        };
+        match attr_type {
+            IFA_ADDRESS => address = ip,
+            IFA_LOCAL => local = ip,
+            _ => {}
+        }
+        rest = rest.get(len.div_ceil(4) * 4..).unwrap_or_default();
+    }
+
+    Some(AddressEvent {
+        change,
+        interface_index,
+        // 点对点网卡上 IFA_ADDRESS 为对端地址，IFA_LOCAL 才是本端地址
+        address: local.or(address),
+        scope,
+    })
+}
+
+/// 根据网卡序号查询网卡名称，网卡已被删除时返回None
+fn interface_name(index: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let name = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if name.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造一条地址消息，属性为 (类型, 值)
+    fn message(message_type: u16, scope: u8, index: u32, attrs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut payload = vec![libc::AF_INET as u8, 32, 0, scope];
+        payload.extend_from_slice(&index.to_ne_bytes());
+        for (attr_type, value) in attrs {
+            payload.extend_from_slice(&((RTATTR_HEADER_LEN + value.len()) as u16).to_ne_bytes());
+            payload.extend_from_slice(&attr_type.to_ne_bytes());
+            payload.extend_from_slice(value);
+            payload.resize(payload.len().div_ceil(4) * 4, 0);
+        }
+        let mut packet = ((NLMSG_HEADER_LEN + payload.len()) as u32)
+            .to_ne_bytes()
+            .to_vec();
+        packet.extend_from_slice(&message_type.to_ne_bytes());
+        packet.extend_from_slice(&[0u8; 10]);
+        packet.extend_from_slice(&payload);
+        packet
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        // PPPoE重连：删除旧地址后添加新地址，IFA_ADDRESS为对端地址
+        let mut buf = message(
+            libc::RTM_DELADDR,
+            libc::RT_SCOPE_UNIVERSE,
+            7,
+            &[(IFA_ADDRESS, &[10, 0, 0, 1]), (IFA_LOCAL, &[100, 64, 1, 2])],
+        );
+        buf.extend(message(
+            libc::RTM_NEWADDR,
+            libc::RT_SCOPE_UNIVERSE,
+            7,
+            &[(IFA_ADDRESS, &[10, 0, 0, 1]), (IFA_LOCAL, &[100, 64, 3, 4])],
+        ));
+        // 链路本地地址和其他类型的消息
+        buf.extend(message(
+            libc::RTM_NEWADDR,
+            libc::RT_SCOPE_LINK,
+            2,
+            &[(IFA_ADDRESS, &[169, 254, 0, 1])],
+        ));
+        buf.extend(message(libc::RTM_NEWLINK, 0, 2, &[]));
+
+        let events = parse_messages(&buf);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].change, AddressChange::Del);
+        assert_eq!(events[0].address, Some("100.64.1.2".parse().unwrap()));
+        assert_eq!(events[1].change, AddressChange::New);
+        assert_eq!(events[1].interface_index, 7);
+        assert_eq!(events[1].address, Some("100.64.3.4".parse().unwrap()));
+        assert!(events[1].is_global());
+        assert!(!events[2].is_global());
+
+        // 截断的消息被忽略
+        assert!(parse_messages(&buf[..10]).is_empty());
+        assert_eq!(interface_name(1).as_deref(), Some("lo"));
+    }
+}
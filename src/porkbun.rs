@@ -0,0 +1,260 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{ProviderConfig, ProviderContext};
+use crate::{DnsProvider, DnsRecord, get_record_type, relative_name};
+
+const PORKBUN_API: &str = "https://api.porkbun.com/api/json/v3";
+
+// ========== Porkbun 相关结构 ==========
+
+#[derive(Serialize)]
+struct PorkbunAuth<'a> {
+    apikey: &'a str,
+    secretapikey: &'a str,
+}
+
+#[derive(Serialize)]
+struct PorkbunEditRequest<'a> {
+    #[serde(flatten)]
+    auth: PorkbunAuth<'a>,
+    content: &'a str,
+    ttl: &'static str,
+}
+
+#[derive(Serialize)]
+struct PorkbunCreateRequest<'a> {
+    #[serde(flatten)]
+    auth: PorkbunAuth<'a>,
+    name: &'a str,
+    #[serde(rename = "type")]
+    record_type: &'a str,
+    content: &'a str,
+    ttl: &'static str,
+}
+
+/// Porkbun 所有接口的响应都包含 status 字段，失败时为 "ERROR" 并附带 message
+#[derive(Deserialize, Debug)]
+struct PorkbunResponse {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    records: Vec<PorkbunRecord>,
+    #[serde(default)]
+    domains: Vec<PorkbunDomain>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PorkbunRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PorkbunDomain {
+    domain: String,
+}
+
+// ========== Porkbun Provider 实现 ==========
+
+pub struct PorkbunProvider {
+    api: String,
+    api_key: String,
+    secret_api_key: String,
+    record_name: String,
+    record_type: &'static str,
+}
+
+impl PorkbunProvider {
+    pub fn new(api_key: String, secret_api_key: String, record_name: String) -> Self {
+        PorkbunProvider {
+            api: PORKBUN_API.to_string(),
+            api_key,
+            secret_api_key,
+            record_name,
+            record_type: "A",
+        }
+    }
+
+    /// retrieveByNameType 等接口中的记录类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    fn auth(&self) -> PorkbunAuth<'_> {
+        PorkbunAuth {
+            apikey: &self.api_key,
+            secretapikey: &self.secret_api_key,
+        }
+    }
+
+    /// 调用Porkbun接口，status不为SUCCESS时返回错误
+    fn call<T: Serialize>(&self, path: &str, body: &T) -> Result<PorkbunResponse, Error> {
+        let client = crate::http_client()?;
+        let res = client
+            .post(format!("{}{path}", self.api))
+            .json(body)
+            .send()?;
+        let text = res.text()?;
+        let response: PorkbunResponse = serde_json::from_str(&text).map_err(|e| {
+            warn!("error parse porkbun result: {text}");
+            anyhow!(e)
+        })?;
+        if response.status != "SUCCESS" {
+            return Err(anyhow!(
+                "Porkbun API error: {}",
+                response.message.unwrap_or(text)
+            ));
+        }
+        Ok(response)
+    }
+
+    /// 获取(注册域名, 子域名)，根域名时子域名为空字符串
+    fn get_zone(&self) -> Result<(String, String), Error> {
+        let response = self.call("/domain/listAll", &self.auth())?;
+        response
+            .domains
+            .into_iter()
+            .filter_map(|d| {
+                relative_name(&self.record_name, &d.domain).map(|name| (d.domain, name))
+            })
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(domain, name)| {
+                let subdomain = if name == "@" { String::new() } else { name };
+                (domain, subdomain)
+            })
+            .ok_or_else(|| anyhow!("No domain found for: {}", self.record_name))
+    }
+}
+
+impl DnsProvider for PorkbunProvider {
+    /// 获取DNS记录，只查询record_type类型的记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let (domain, subdomain) = self.get_zone()?;
+        let response = self.call(
+            &format!(
+                "/dns/retrieveByNameType/{domain}/{}/{subdomain}",
+                self.record_type
+            ),
+            &self.auth(),
+        )?;
+        match response.records.into_iter().next() {
+            Some(record) => {
+                info!("current porkbun record is {:?}", record);
+                Ok(Some(DnsRecord {
+                    id: record.id,
+                    name: record.name,
+                    value: record.content,
+                    record_type: record.record_type,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, record: &DnsRecord) -> Result<(), Error> {
+        let (domain, subdomain) = self.get_zone()?;
+        self.call(
+            &format!(
+                "/dns/editByNameType/{domain}/{}/{subdomain}",
+                record.record_type
+            ),
+            &PorkbunEditRequest {
+                auth: self.auth(),
+                content: current_ip,
+                ttl: "600",
+            },
+        )?;
+        debug!("porkbun modify result: success");
+        Ok(())
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        let (domain, subdomain) = self.get_zone()?;
+        self.call(
+            &format!("/dns/create/{domain}"),
+            &PorkbunCreateRequest {
+                auth: self.auth(),
+                name: &subdomain,
+                record_type: get_record_type(current_ip),
+                content: current_ip,
+                ttl: "600",
+            },
+        )?;
+        debug!("porkbun add result: success");
+        Ok(())
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的Porkbun配置
+#[derive(Deserialize, Debug)]
+pub struct PorkbunConfig {
+    /// Porkbun API Key
+    pub porkbun_api_key: String,
+    /// Porkbun Secret API Key
+    pub porkbun_secret_api_key: String,
+}
+
+impl ProviderConfig for PorkbunConfig {
+    const NAME: &'static str = "porkbun";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            PorkbunProvider::new(
+                self.porkbun_api_key,
+                self.porkbun_secret_api_key,
+                context.domain.to_string(),
+            )
+            .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DnsUpdateResult;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_update_dns_record() {
+        const DOMAINS: &str = r#"{"status": "SUCCESS", "domains": [{"domain": "example.com"}]}"#;
+        let (address, handle) = serve_http(vec![
+            DOMAINS,
+            r#"{"status": "SUCCESS", "records": []}"#,
+            DOMAINS,
+            r#"{"status": "SUCCESS", "id": 1}"#,
+        ]);
+        let mut provider = PorkbunProvider::new(
+            "pk".to_string(),
+            "sk".to_string(),
+            "home.example.com".to_string(),
+        )
+        .record_type("AAAA");
+        provider.api = format!("http://{address}");
+
+        // 没有AAAA记录时新建，不删除A记录
+        let result = provider.update_dns_record("2001:db8::1").unwrap();
+        assert!(matches!(result, DnsUpdateResult::Created));
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(
+            requests[1]
+                .0
+                .starts_with("POST /dns/retrieveByNameType/example.com/AAAA/home ")
+        );
+        assert!(requests[3].0.starts_with("POST /dns/create/example.com "));
+        assert!(requests[3].1.contains(r#""type":"AAAA""#));
+    }
+}
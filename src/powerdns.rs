@@ -0,0 +1,464 @@
+use anyhow::{Error, anyhow};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::registry::{BatchContext, ProviderConfig, ProviderContext};
+use crate::{BatchDnsProvider, DnsProvider, DnsRecord, DnsUpdateResult, get_record_type};
+
+// ========== PowerDNS 相关结构 ==========
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PowerdnsZone {
+    id: String,
+    name: String,
+    #[serde(default)]
+    rrsets: Vec<PowerdnsRrset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PowerdnsRrset {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    #[serde(default)]
+    ttl: Option<u32>,
+    #[serde(default)]
+    records: Vec<PowerdnsRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PowerdnsRecord {
+    content: String,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Serialize)]
+struct PowerdnsPatchRequest {
+    rrsets: Vec<PowerdnsRrsetChange>,
+}
+
+#[derive(Serialize)]
+struct PowerdnsRrsetChange {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+    changetype: &'static str,
+    records: Vec<PowerdnsRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PowerdnsErrorResponse {
+    error: String,
+}
+
+// ========== PowerDNS Provider 实现 ==========
+
+pub struct PowerdnsProvider {
+    api_url: String,
+    api_key: String,
+    server_id: String,
+    zone: Option<String>,
+    record_name: String,
+    record_type: &'static str,
+    notify: bool,
+    rectify: bool,
+}
+
+impl PowerdnsProvider {
+    /// api_url 为 PowerDNS Authoritative 的 webserver 地址，如 "http://127.0.0.1:8081"
+    pub fn new(api_url: String, api_key: String, record_name: String) -> Self {
+        PowerdnsProvider {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            api_key,
+            server_id: "localhost".to_string(),
+            zone: None,
+            record_name,
+            record_type: "A",
+            notify: false,
+            rectify: false,
+        }
+    }
+
+    /// 设置server id，默认为 "localhost"
+    pub fn server_id(mut self, server_id: String) -> Self {
+        self.server_id = server_id;
+        self
+    }
+
+    /// 指定zone名称，未指定时从服务器的zone列表中按最长后缀匹配
+    pub fn zone(mut self, zone: Option<String>) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// 替换的rrset类型，默认为 "A"
+    pub fn record_type(mut self, record_type: &'static str) -> Self {
+        self.record_type = record_type;
+        self
+    }
+
+    /// 记录变更后是否对zone发送NOTIFY
+    pub fn notify(mut self, notify: bool) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /// 记录变更后是否对zone执行rectify
+    pub fn rectify(mut self, rectify: bool) -> Self {
+        self.rectify = rectify;
+        self
+    }
+
+    /// 转换为以点结尾的规范名称
+    /// 例如: "sub.example.com" -> "sub.example.com."
+    fn canonical(name: &str) -> String {
+        if name.ends_with('.') {
+            name.to_string()
+        } else {
+            format!("{name}.")
+        }
+    }
+
+    fn zones_url(&self) -> String {
+        format!("{}/api/v1/servers/{}/zones", self.api_url, self.server_id)
+    }
+
+    /// 获取zone id，未配置zone时查询zone列表并选择与记录名称匹配的最长后缀
+    fn get_zone_id(&self) -> Result<String, Error> {
+        if let Some(zone) = &self.zone {
+            return Ok(Self::canonical(zone));
+        }
+
+        let record_name = Self::canonical(&self.record_name);
+        let client = crate::http_client()?;
+        let response = client
+            .get(self.zones_url())
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .map_err(|e| anyhow!("Failed to query zone list: {}", e))?;
+        let text = Self::check_response(response)?;
+        let zones: Vec<PowerdnsZone> = serde_json::from_str(&text).map_err(|e| {
+            warn!("error parse powerdns zone list: {text}");
+            anyhow!(e)
+        })?;
+
+        zones
+            .into_iter()
+            .filter(|zone| {
+                record_name == zone.name || record_name.ends_with(&format!(".{}", zone.name))
+            })
+            .max_by_key(|zone| zone.name.len())
+            .map(|zone| {
+                debug!("Found powerdns zone for {}: {}", record_name, zone.id);
+                zone.id
+            })
+            .ok_or_else(|| anyhow!("No zone found for domain: {}", self.record_name))
+    }
+
+    /// 检查HTTP状态码，非2xx时解析PowerDNS的错误信息
+    fn check_response(response: reqwest::blocking::Response) -> Result<String, Error> {
+        let status = response.status();
+        let text = response.text()?;
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = serde_json::from_str::<PowerdnsErrorResponse>(&text)
+                .map(|e| e.error)
+                .unwrap_or(text);
+            Err(anyhow!("PowerDNS API error ({}): {}", status, message))
+        }
+    }
+
+    /// 提交rrset变更，并按配置执行rectify和NOTIFY，rectify和NOTIFY失败时只记录警告
+    fn patch_rrsets(&self, rrsets: Vec<PowerdnsRrsetChange>) -> Result<(), Error> {
+        let zone_id = self.get_zone_id()?;
+        let client = crate::http_client()?;
+        let zone_url = format!("{}/{}", self.zones_url(), zone_id);
+
+        let res = client
+            .patch(&zone_url)
+            .header("X-API-Key", &self.api_key)
+            .json(&PowerdnsPatchRequest { rrsets })
+            .send()?;
+        Self::check_response(res)?;
+        debug!("powerdns patch result: success");
+
+        if self.rectify {
+            let result = client
+                .put(format!("{zone_url}/rectify"))
+                .header("X-API-Key", &self.api_key)
+                .send()
+                .map_err(Error::from)
+                .and_then(Self::check_response);
+            match result {
+                Ok(_) => info!("powerdns zone {zone_id} rectified"),
+                Err(e) => warn!("powerdns rectify zone {zone_id} failed: {e}"),
+            }
+        }
+
+        if self.notify {
+            let result = client
+                .put(format!("{zone_url}/notify"))
+                .header("X-API-Key", &self.api_key)
+                .send()
+                .map_err(Error::from)
+                .and_then(Self::check_response);
+            match result {
+                Ok(_) => info!("powerdns zone {zone_id} notify queued"),
+                Err(e) => warn!("powerdns notify zone {zone_id} failed: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 获取zone及其全部rrset
+    fn fetch_zone(&self) -> Result<PowerdnsZone, Error> {
+        let zone_id = self.get_zone_id()?;
+        let client = crate::http_client()?;
+        let url = format!("{}/{}", self.zones_url(), zone_id);
+
+        let res = client.get(&url).header("X-API-Key", &self.api_key).send()?;
+        let text = Self::check_response(res)?;
+        serde_json::from_str(&text).map_err(|err| {
+            warn!("error parse powerdns result: {text}");
+            anyhow!(err)
+        })
+    }
+
+    /// 在zone中查找域名指定类型的记录
+    fn find_record(zone: &PowerdnsZone, record_name: &str, record_type: &str) -> Option<DnsRecord> {
+        let record_name = Self::canonical(record_name);
+        let rrset = zone
+            .rrsets
+            .iter()
+            .find(|rrset| rrset.name == record_name && rrset.record_type == record_type)?;
+        let record = rrset.records.iter().find(|r| !r.disabled)?;
+        info!("current powerdns rrset is {:?}", rrset);
+        Some(DnsRecord {
+            id: rrset.name.clone(),
+            name: rrset.name.clone(),
+            value: record.content.clone(),
+            record_type: rrset.record_type.clone(),
+        })
+    }
+
+    /// 将记录更新为current_ip的rrset变更，只替换current_ip所属地址族的rrset
+    fn replace_change(record_name: &str, current_ip: &str) -> PowerdnsRrsetChange {
+        PowerdnsRrsetChange {
+            name: Self::canonical(record_name),
+            record_type: get_record_type(current_ip).to_string(),
+            ttl: Some(60),
+            changetype: "REPLACE",
+            records: vec![PowerdnsRecord {
+                content: current_ip.to_string(),
+                disabled: false,
+            }],
+        }
+    }
+}
+
+impl DnsProvider for PowerdnsProvider {
+    /// 获取DNS记录
+    fn get_record(&self) -> Result<Option<DnsRecord>, Error> {
+        let zone = self.fetch_zone()?;
+        Ok(Self::find_record(
+            &zone,
+            &self.record_name,
+            self.record_type,
+        ))
+    }
+
+    /// 修改DNS记录
+    fn modify_record(&self, current_ip: &str, _record: &DnsRecord) -> Result<(), Error> {
+        self.patch_rrsets(vec![Self::replace_change(&self.record_name, current_ip)])
+    }
+
+    /// 添加DNS记录
+    fn add_record(&self, current_ip: &str) -> Result<(), Error> {
+        self.patch_rrsets(vec![Self::replace_change(&self.record_name, current_ip)])
+    }
+}
+
+/// 批量更新同一zone下的多个域名：一次读取zone，所有变更合并为一次PATCH
+pub struct PowerdnsBatchProvider {
+    provider: PowerdnsProvider,
+    record_names: Vec<String>,
+}
+
+impl PowerdnsBatchProvider {
+    /// provider 用于访问API和确定zone，record_names 需属于同一zone
+    pub fn new(provider: PowerdnsProvider, record_names: Vec<String>) -> Self {
+        PowerdnsBatchProvider {
+            provider,
+            record_names,
+        }
+    }
+}
+
+impl BatchDnsProvider for PowerdnsBatchProvider {
+    fn update_dns_records(&self, current_ip: &str) -> Result<Vec<DnsUpdateResult>, Error> {
+        let zone = self.provider.fetch_zone()?;
+        let record_type = get_record_type(current_ip);
+        let mut rrsets = Vec::new();
+        let mut results = Vec::new();
+        for record_name in &self.record_names {
+            // 与单个更新一致，record_id为rrset的名称
+            let result = match PowerdnsProvider::find_record(&zone, record_name, record_type) {
+                Some(record) if record.value == current_ip => DnsUpdateResult::Unchanged {
+                    record_id: Some(record.id),
+                },
+                Some(record) => {
+                    rrsets.push(PowerdnsProvider::replace_change(record_name, current_ip));
+                    DnsUpdateResult::Changed {
+                        old_ip: record.value,
+                        record_id: Some(record.id),
+                    }
+                }
+                None => {
+                    rrsets.push(PowerdnsProvider::replace_change(record_name, current_ip));
+                    DnsUpdateResult::Created
+                }
+            };
+            results.push(result);
+        }
+        if !rrsets.is_empty() {
+            info!(
+                "patch {} powerdns rrsets in zone {}",
+                rrsets.len(),
+                zone.name
+            );
+            self.provider.patch_rrsets(rrsets)?;
+        }
+        Ok(results)
+    }
+}
+
+// ========== 配置文件注册 ==========
+
+/// 配置文件中的PowerDNS配置
+#[derive(Deserialize, Debug)]
+pub struct PowerdnsConfig {
+    /// PowerDNS API地址 (如: "http://127.0.0.1:8081")
+    pub powerdns_url: String,
+    /// PowerDNS API Key
+    pub powerdns_api_key: String,
+    /// PowerDNS server id，未设置时为 "localhost"
+    #[serde(default = "default_server_id")]
+    pub powerdns_server_id: String,
+    /// PowerDNS zone，未设置时从zone列表中按最长后缀匹配
+    #[serde(default)]
+    pub powerdns_zone: Option<String>,
+    /// 记录变更后是否向zone发送NOTIFY
+    #[serde(default)]
+    pub powerdns_notify: bool,
+    /// 记录变更后是否对zone执行rectify
+    #[serde(default)]
+    pub powerdns_rectify: bool,
+}
+
+fn default_server_id() -> String {
+    "localhost".to_string()
+}
+
+impl PowerdnsConfig {
+    fn provider(self, record_name: String) -> PowerdnsProvider {
+        PowerdnsProvider::new(self.powerdns_url, self.powerdns_api_key, record_name)
+            .server_id(self.powerdns_server_id)
+            .zone(self.powerdns_zone)
+            .notify(self.powerdns_notify)
+            .rectify(self.powerdns_rectify)
+    }
+}
+
+impl ProviderConfig for PowerdnsConfig {
+    const NAME: &'static str = "powerdns";
+    const SELECTS_BY_FAMILY: bool = true;
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error> {
+        Ok(Box::new(
+            self.provider(context.domain.to_string())
+                .record_type(get_record_type(context.current_ip)),
+        ))
+    }
+
+    fn build_batch(
+        self,
+        context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error> {
+        let first = context
+            .domains
+            .first()
+            .ok_or_else(|| anyhow!("no domains to update"))?;
+        let provider = self.provider(first.clone());
+        Ok(Some(Box::new(PowerdnsBatchProvider::new(
+            provider,
+            context.domains.to_vec(),
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::serve_http;
+
+    #[test]
+    fn test_batch_update() {
+        let (address, handle) = serve_http(vec![
+            r#"{"id": "example.com.", "name": "example.com.", "rrsets": [
+                {"name": "home.example.com.", "type": "A", "records": [{"content": "1.2.3.4"}]},
+                {"name": "home.example.com.", "type": "AAAA", "records": [{"content": "2001:db8::1"}]},
+                {"name": "nas.example.com.", "type": "AAAA", "records": [{"content": "2001:db8::2"}]}
+            ]}"#,
+            "",
+        ]);
+        // NOTIFY请求无法连接，不影响已经成功的更新
+        let provider = PowerdnsProvider::new(
+            format!("http://{address}"),
+            "key".to_string(),
+            "home.example.com".to_string(),
+        )
+        .zone(Some("example.com".to_string()))
+        .notify(true);
+        let batch = PowerdnsBatchProvider::new(
+            provider,
+            vec![
+                "home.example.com".to_string(),
+                "nas.example.com".to_string(),
+            ],
+        );
+        let results = batch.update_dns_records("2001:db8::2").unwrap();
+        assert!(matches!(
+            &results[0],
+            DnsUpdateResult::Changed { old_ip, record_id: Some(id) }
+                if old_ip == "2001:db8::1" && id == "home.example.com."
+        ));
+        assert!(matches!(
+            &results[1],
+            DnsUpdateResult::Unchanged { record_id: Some(id) } if id == "nas.example.com."
+        ));
+
+        // 只替换AAAA记录，A记录保持不变
+        let requests = handle.join().unwrap();
+        assert!(
+            requests[1]
+                .0
+                .starts_with("PATCH /api/v1/servers/localhost/zones/example.com. ")
+        );
+        let patch: serde_json::Value = serde_json::from_str(&requests[1].1).unwrap();
+        assert_eq!(
+            patch,
+            serde_json::json!({"rrsets": [{
+                "name": "home.example.com.",
+                "type": "AAAA",
+                "ttl": 60,
+                "changetype": "REPLACE",
+                "records": [{"content": "2001:db8::2", "disabled": false}]
+            }]})
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{self, Read, Write};
+use std::process::{Command, Output, Stdio};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+// ========== 带超时执行外部命令 ==========
+
+/// 执行命令并收集输出，超过timeout仍未结束时结束进程并返回None
+///
+/// stdin的写入和stdout/stderr的读取都在后台线程中进行，
+/// 输出较多时子进程不会因管道写满而阻塞，不读取stdin的程序也能按超时结束
+pub(crate) fn output_with_timeout(
+    cmd: &mut Command,
+    input: Option<Vec<u8>>,
+    timeout: Duration,
+) -> io::Result<Option<Output>> {
+    let stdin = if input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    let mut child = cmd
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        thread::spawn(move || {
+            if let Err(e) = stdin.write_all(&input) {
+                debug!("failed to write command stdin: {e}");
+            }
+        });
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        sleep(Duration::from_millis(50));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_output_with_timeout() {
+        let output = output_with_timeout(
+            Command::new("bash").arg("-c").arg("cat; echo err >&2"),
+            Some(b"hello".to_vec()),
+            Duration::from_secs(10),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello");
+        assert_eq!(output.stderr, b"err\n");
+
+        // 超时后结束进程
+        let start = Instant::now();
+        let output = output_with_timeout(
+            Command::new("bash").arg("-c").arg("sleep 10"),
+            None,
+            Duration::from_millis(200),
+        )
+        .unwrap();
+        assert!(output.is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
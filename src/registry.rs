@@ -0,0 +1,260 @@
+use anyhow::{Error, anyhow};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use crate::{BatchDnsProvider, DnsProvider};
+
+/// 构造provider时可用的上下文
+pub struct ProviderContext<'a> {
+    /// 完整域名
+    pub domain: &'a str,
+    /// 当前检测到的IP
+    pub current_ip: &'a str,
+}
+
+/// 批量构造provider时可用的上下文
+pub struct BatchContext<'a> {
+    /// 同一zone下的全部完整域名
+    pub domains: &'a [String],
+    /// 当前检测到的IP
+    pub current_ip: &'a str,
+}
+
+/// provider的配置结构，从配置文件中反序列化得到，并负责构造对应的provider
+///
+/// 字段名即配置文件中的键名（如 `cloudflare_token`），
+/// 缺少必填字段时反序列化失败，即视为配置校验不通过
+pub trait ProviderConfig: DeserializeOwned + 'static {
+    /// provider名称，对应配置文件中的 provider 字段
+    const NAME: &'static str;
+
+    /// 是否按IP的地址族分别查找和修改A/AAAA记录，为false时更新AAAA记录可能覆盖同名的A记录
+    const SELECTS_BY_FAMILY: bool = false;
+
+    /// 反序列化之外的额外校验
+    fn validate(&self, _domain: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn build(self, context: &ProviderContext) -> Result<Box<dyn DnsProvider>, Error>;
+
+    /// 构造批量更新多个域名的provider，不支持批量更新时返回None，由调用方逐个域名调用build
+    fn build_batch(
+        self,
+        _context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error> {
+        Ok(None)
+    }
+}
+
+/// 类型擦除后的 ProviderConfig，便于放进同一个注册表
+trait ProviderFactory: Send + Sync {
+    fn selects_by_family(&self) -> bool;
+    fn validate(&self, params: &Map<String, Value>, domain: &str) -> Result<(), Error>;
+    fn create(
+        &self,
+        params: &Map<String, Value>,
+        context: &ProviderContext,
+    ) -> Result<Box<dyn DnsProvider>, Error>;
+    fn create_batch(
+        &self,
+        params: &Map<String, Value>,
+        context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error>;
+}
+
+struct Factory<C>(PhantomData<fn() -> C>);
+
+impl<C: ProviderConfig> Factory<C> {
+    fn parse(params: &Map<String, Value>) -> Result<C, Error> {
+        serde_json::from_value(Value::Object(params.clone()))
+            .map_err(|e| anyhow!("invalid {} config: {}", C::NAME, e))
+    }
+}
+
+impl<C: ProviderConfig> ProviderFactory for Factory<C> {
+    fn selects_by_family(&self) -> bool {
+        C::SELECTS_BY_FAMILY
+    }
+
+    fn validate(&self, params: &Map<String, Value>, domain: &str) -> Result<(), Error> {
+        Self::parse(params)?.validate(domain)
+    }
+
+    fn create(
+        &self,
+        params: &Map<String, Value>,
+        context: &ProviderContext,
+    ) -> Result<Box<dyn DnsProvider>, Error> {
+        Self::parse(params)?.build(context)
+    }
+
+    fn create_batch(
+        &self,
+        params: &Map<String, Value>,
+        context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error> {
+        Self::parse(params)?.build_batch(context)
+    }
+}
+
+/// provider注册表：按名称查找provider的配置结构和构造方法
+pub struct ProviderRegistry {
+    factories: BTreeMap<&'static str, Box<dyn ProviderFactory>>,
+}
+
+impl ProviderRegistry {
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        ProviderRegistry {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// 创建包含全部内置provider的注册表
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register::<crate::azure::AzureConfig>()
+            .register::<crate::cloudflare::CloudflareConfig>()
+            .register::<crate::custom::CustomProviderConfig>()
+            .register::<crate::desec::DesecConfig>()
+            .register::<crate::digitalocean::DigitaloceanConfig>()
+            .register::<crate::dnspod::DnspodConfig>()
+            .register::<crate::duckdns::DuckdnsConfig>()
+            .register::<crate::file::FileConfig>()
+            .register::<crate::gandi::GandiConfig>()
+            .register::<crate::gcloud::GcloudConfig>()
+            .register::<crate::hetzner::HetznerConfig>()
+            .register::<crate::huaweicloud::HuaweicloudConfig>()
+            .register::<crate::namecheap::NamecheapConfig>()
+            .register::<crate::porkbun::PorkbunConfig>()
+            .register::<crate::powerdns::PowerdnsConfig>();
+        registry
+    }
+
+    /// 注册provider，同名的provider会被覆盖
+    pub fn register<C: ProviderConfig>(&mut self) -> &mut Self {
+        self.factories
+            .insert(C::NAME, Box::new(Factory::<C>(PhantomData)));
+        self
+    }
+
+    /// 已注册的provider名称（按字母排序）
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.factories.keys().copied()
+    }
+
+    fn factory(&self, name: &str) -> Result<&dyn ProviderFactory, Error> {
+        self.factories.get(name).map(Box::as_ref).ok_or_else(|| {
+            anyhow!(
+                "unknown provider \"{}\", supported: {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+
+    /// provider是否按IP的地址族分别查找和修改A/AAAA记录
+    pub fn selects_by_family(&self, name: &str) -> Result<bool, Error> {
+        Ok(self.factory(name)?.selects_by_family())
+    }
+
+    /// 校验provider配置，params为合并了默认值后的配置项
+    pub fn validate(
+        &self,
+        name: &str,
+        params: &Map<String, Value>,
+        domain: &str,
+    ) -> Result<(), Error> {
+        self.factory(name)?.validate(params, domain)
+    }
+
+    /// 根据配置构造provider
+    pub fn create(
+        &self,
+        name: &str,
+        params: &Map<String, Value>,
+        context: &ProviderContext,
+    ) -> Result<Box<dyn DnsProvider>, Error> {
+        self.factory(name)?.create(params, context)
+    }
+
+    /// 根据配置构造批量更新的provider，provider不支持批量更新时返回None
+    pub fn create_batch(
+        &self,
+        name: &str,
+        params: &Map<String, Value>,
+        context: &BatchContext,
+    ) -> Result<Option<Box<dyn BatchDnsProvider>>, Error> {
+        self.factory(name)?.create_batch(params, context)
+    }
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self::with_builtin()
+    }
+}
+
+/// 合并全局默认配置与域名配置：全局配置中 `default_xxx` 作为 `xxx` 的默认值，域名配置优先
+pub fn merge_params(
+    defaults: &Map<String, Value>,
+    params: &Map<String, Value>,
+) -> Map<String, Value> {
+    let mut merged: Map<String, Value> = defaults
+        .iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix("default_")
+                .map(|key| (key.to_string(), value.clone()))
+        })
+        .collect();
+    merged.extend(params.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_registry() {
+        let defaults = json!({
+            "default_cloudflare_token": "global",
+            "default_huaweicloud_access_key": "ak",
+            "unrelated": "ignored",
+        });
+        let params = json!({ "cloudflare_token": "domain" });
+        let merged = merge_params(defaults.as_object().unwrap(), params.as_object().unwrap());
+        assert_eq!(merged["cloudflare_token"], "domain");
+        assert_eq!(merged["huaweicloud_access_key"], "ak");
+        assert!(!merged.contains_key("unrelated"));
+
+        let registry = ProviderRegistry::with_builtin();
+        registry
+            .validate("cloudflare", &merged, "www.example.com")
+            .unwrap();
+        // 缺少huaweicloud_secret_key
+        let err = registry
+            .validate("huaweicloud", &merged, "www.example.com")
+            .unwrap_err();
+        assert!(err.to_string().contains("huaweicloud_secret_key"));
+        // dnspod 需要能拆分域名
+        let params = json!({ "dnspod_token": "id,secret" });
+        assert!(
+            registry
+                .validate("dnspod", params.as_object().unwrap(), "invalid")
+                .is_err()
+        );
+        assert!(
+            registry
+                .validate("nonexistent", &merged, "www.example.com")
+                .is_err()
+        );
+        assert!(registry.selects_by_family("file").unwrap());
+        assert!(!registry.selects_by_family("cloudflare").unwrap());
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::{Error, anyhow};
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// ========== 重新加载配置的触发 (SIGHUP 和 inotify) ==========
+
+/// inotify_event 头部的长度：wd(4) mask(4) cookie(4) len(4)，之后为len字节的文件名
+const INOTIFY_EVENT_HEADER_LEN: usize = 16;
+
+/// SIGHUP处理函数写入的管道，-1表示未订阅
+static SIGHUP_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_sighup(_: libc::c_int) {
+    // 信号处理函数中只能调用异步信号安全的函数，写管道通知等待的线程
+    let fd = SIGHUP_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        unsafe { libc::write(fd, [1u8].as_ptr() as *const libc::c_void, 1) };
+    }
+}
+
+/// 接收SIGHUP信号，安装后SIGHUP不再终止进程
+pub struct SighupListener {
+    fd: OwnedFd,
+}
+
+impl SighupListener {
+    /// 安装SIGHUP处理函数，进程中只能订阅一次
+    pub fn subscribe() -> Result<Self, Error> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+            return Err(anyhow!(
+                "failed to create pipe: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        // 管道满时丢弃通知，不能阻塞信号处理函数
+        if unsafe { libc::fcntl(write.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) } < 0 {
+            return Err(anyhow!(
+                "failed to set pipe non-blocking: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        if SIGHUP_PIPE
+            .compare_exchange(-1, write.as_raw_fd(), Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(anyhow!("SIGHUP is already subscribed"));
+        }
+        // 写端在进程退出前一直保持打开，避免信号处理函数写入被复用的fd
+        let _ = write.into_raw_fd();
+
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_sighup as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        let result = unsafe {
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGHUP, &action, std::ptr::null_mut())
+        };
+        if result < 0 {
+            return Err(anyhow!(
+                "failed to install SIGHUP handler: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        Ok(SighupListener { fd: read })
+    }
+
+    /// 阻塞等待下一次SIGHUP，短时间内的多次信号可能合并为一次
+    pub fn wait(&self) -> Result<(), Error> {
+        let mut buf = [0u8; 64];
+        loop {
+            let len = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+            match len {
+                1.. => return Ok(()),
+                0 => return Err(anyhow!("SIGHUP pipe closed")),
+                _ => {
+                    let e = io::Error::last_os_error();
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(anyhow!("failed to read SIGHUP pipe: {e}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 通过inotify监听文件的修改
+///
+/// 监听的是文件所在的目录，编辑器先写临时文件再rename覆盖时同样可以收到事件
+pub struct FileWatcher {
+    fd: OwnedFd,
+    file_name: OsString,
+}
+
+impl FileWatcher {
+    pub fn watch(path: &Path) -> Result<Self, Error> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid file path: {}", path.display()))?
+            .to_os_string();
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(anyhow!(
+                "failed to create inotify instance: {}",
+                io::Error::last_os_error()
+            ));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let dir_name = CString::new(dir.as_os_str().as_bytes())?;
+        let result = unsafe {
+            libc::inotify_add_watch(
+                fd.as_raw_fd(),
+                dir_name.as_ptr(),
+                libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO,
+            )
+        };
+        if result < 0 {
+            return Err(anyhow!(
+                "failed to watch {}: {}",
+                dir.display(),
+                io::Error::last_os_error()
+            ));
+        }
+        Ok(FileWatcher { fd, file_name })
+    }
+
+    /// 阻塞等待文件被写入或被rename覆盖
+    pub fn wait(&self) -> Result<(), Error> {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            let len = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+            if len < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(anyhow!("failed to read inotify events: {e}"));
+            }
+            if parse_event_names(&buf[..len as usize])
+                .iter()
+                .any(|name| *name == self.file_name)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// 解析一次read收到的全部inotify事件，返回其中的文件名
+fn parse_event_names(buf: &[u8]) -> Vec<&OsStr> {
+    let mut names = Vec::new();
+    let mut rest = buf;
+    while rest.len() >= INOTIFY_EVENT_HEADER_LEN {
+        let len = u32::from_ne_bytes(rest[12..16].try_into().unwrap()) as usize;
+        let Some(name) = rest.get(INOTIFY_EVENT_HEADER_LEN..INOTIFY_EVENT_HEADER_LEN + len) else {
+            break;
+        };
+        // 文件名以NUL结尾并填充对齐
+        let name = name.split(|&b| b == 0).next().unwrap_or_default();
+        if !name.is_empty() {
+            names.push(OsStr::from_bytes(name));
+        }
+        rest = &rest[INOTIFY_EVENT_HEADER_LEN + len..];
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_reload() {
+        // 构造两个事件：目录本身的事件 (无文件名) 和 config.toml
+        let mut buf = Vec::new();
+        for name in [&b""[..], b"config.toml"] {
+            let len = if name.is_empty() { 0 } else { 16 };
+            buf.extend_from_slice(&1i32.to_ne_bytes());
+            buf.extend_from_slice(&libc::IN_CLOSE_WRITE.to_ne_bytes());
+            buf.extend_from_slice(&0u32.to_ne_bytes());
+            buf.extend_from_slice(&(len as u32).to_ne_bytes());
+            let mut padded = name.to_vec();
+            padded.resize(len, 0);
+            buf.extend_from_slice(&padded);
+        }
+        assert_eq!(parse_event_names(&buf), [OsStr::new("config.toml")]);
+        assert!(parse_event_names(&buf[..20]).is_empty());
+
+        // rename覆盖配置文件时收到事件
+        let dir = std::env::temp_dir().join(format!("ddns-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let watcher = FileWatcher::watch(&path).unwrap();
+        fs::write(dir.join("other.toml"), "").unwrap();
+        fs::write(dir.join("config.toml.tmp"), "").unwrap();
+        fs::rename(dir.join("config.toml.tmp"), &path).unwrap();
+        watcher.wait().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // SIGHUP不再终止进程
+        let listener = SighupListener::subscribe().unwrap();
+        unsafe { libc::raise(libc::SIGHUP) };
+        listener.wait().unwrap();
+        assert!(SighupListener::subscribe().is_err());
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::Error;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::schedule::deserialize_duration;
+
+// ========== 重试和退避 ==========
+
+/// 重试、退避和熔断的配置，配置文件中写作 `[retry]`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// 临时性网络错误时立即重试的次数，默认为2，0表示不重试
+    pub transient_retries: u32,
+    /// 立即重试前的等待时间，默认为1秒，之后每次翻倍
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub transient_retry_delay: Duration,
+    /// 连续失败时检查间隔的上限，默认为30分钟
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max_backoff: Duration,
+    /// 连续多少次认证失败后暂停该域名，默认为3，0表示不暂停
+    pub auth_failure_threshold: u32,
+    /// 认证失败后暂停的时长，默认为1小时
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub auth_failure_pause: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            transient_retries: 2,
+            transient_retry_delay: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30 * 60),
+            auth_failure_threshold: 3,
+            auth_failure_pause: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// 错误的类型，决定重试的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// 超时、连接失败、5xx等临时性错误，可以立即重试
+    Transient,
+    /// 其他错误，按退避间隔重试
+    Other,
+    /// 认证失败 (401/403、token无效等)，重试无法恢复，连续多次后暂停
+    Auth,
+}
+
+/// 认证失败的错误信息中常见的关键词 (小写)
+const AUTH_PATTERNS: &[&str] = &[
+    "unauthorized",
+    "forbidden",
+    "authentication",
+    "authfailure",
+    "invalid access token",
+    "invalid token",
+    "invalid api key",
+    "access denied",
+    "permission denied",
+];
+
+/// 临时性错误的错误信息中常见的关键词 (小写)
+const TRANSIENT_PATTERNS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "error sending request",
+    "dns error",
+    "temporarily unavailable",
+    "bad gateway",
+    "service unavailable",
+    "gateway timeout",
+];
+
+/// 判断错误的类型：优先根据错误链中的 reqwest::Error 和 io::Error，其次根据错误信息中的状态码和关键词
+pub fn classify(error: &Error) -> ErrorKind {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = e.status() {
+                return classify_status(status.as_u16());
+            }
+            if e.is_timeout() || e.is_connect() || e.is_request() {
+                return ErrorKind::Transient;
+            }
+        }
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            match e.kind() {
+                io::ErrorKind::TimedOut
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::Interrupted => return ErrorKind::Transient,
+                _ => {}
+            }
+        }
+    }
+
+    let message = format!("{error:#}").to_lowercase();
+    if let Some(status) = message_status(&message) {
+        return classify_status(status);
+    }
+    if AUTH_PATTERNS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+    {
+        ErrorKind::Auth
+    } else if TRANSIENT_PATTERNS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+    {
+        ErrorKind::Transient
+    } else {
+        ErrorKind::Other
+    }
+}
+
+/// 从provider的错误信息中取出HTTP状态码，只识别紧跟在 "API error " 或左括号之后的状态码，
+/// 如 "API error (401 Unauthorized): ..." 和 "API error 503: ..."，域名、记录内容等其他位置的数字不视为状态码
+fn message_status(message: &str) -> Option<u16> {
+    let after = |marker: &'static str| {
+        message
+            .match_indices(marker)
+            .filter_map(move |(i, _)| leading_status(&message[i + marker.len()..]))
+    };
+    after("api error ").chain(after(" (")).next()
+}
+
+/// 开头的三位数字状态码，其后须为空格、冒号或右括号
+fn leading_status(text: &str) -> Option<u16> {
+    let code = text.get(..3)?;
+    if !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if !matches!(text[3..].chars().next(), None | Some(' ' | ':' | ')')) {
+        return None;
+    }
+    code.parse().ok().filter(|code| (100..=599).contains(code))
+}
+
+fn classify_status(status: u16) -> ErrorKind {
+    match status {
+        401 | 403 => ErrorKind::Auth,
+        // 429 需要降低频率，不立即重试
+        502..=504 => ErrorKind::Transient,
+        _ => ErrorKind::Other,
+    }
+}
+
+impl RetryConfig {
+    /// 执行f，遇到临时性错误时等待一小段时间后重试，最多重试transient_retries次
+    pub fn retry_transient<T>(&self, mut f: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let mut attempt = 0;
+        loop {
+            match f() {
+                Err(e)
+                    if attempt < self.transient_retries && classify(&e) == ErrorKind::Transient =>
+                {
+                    let delay = jitter(self.transient_retry_delay * 2u32.saturating_pow(attempt));
+                    attempt += 1;
+                    warn!(
+                        "Transient error, retrying in {:.1}s ({}/{}): {}",
+                        delay.as_secs_f64(),
+                        attempt,
+                        self.transient_retries,
+                        e
+                    );
+                    sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 连续失败failures次后的检查间隔：interval翻倍failures次，不超过max_backoff，且不小于interval
+    pub fn backoff(&self, interval: Duration, failures: u32) -> Duration {
+        let delay = interval
+            .checked_mul(2u32.saturating_pow(failures.min(31)))
+            .unwrap_or(Duration::MAX)
+            .min(self.max_backoff);
+        jitter(delay).max(interval)
+    }
+}
+
+/// 在 [0.8, 1.2) 倍之间随机调整等待时间，避免多个域名或多个实例同时重试
+fn jitter(delay: Duration) -> Duration {
+    let mut bytes = [0u8; 4];
+    let factor = match ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut bytes)
+    {
+        Ok(()) => 0.8 + 0.4 * (u32::from_le_bytes(bytes) as f64 / (u32::MAX as f64 + 1.0)),
+        Err(_) => 1.0,
+    };
+    delay.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_retry() {
+        let kind = |message: &str| classify(&anyhow!("{message}"));
+        assert_eq!(
+            kind("Hetzner API error 401 Unauthorized: invalid token"),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            kind("Cloudflare API error: 10000: Authentication error"),
+            ErrorKind::Auth
+        );
+        assert_eq!(kind("PowerDNS API error 503: busy"), ErrorKind::Transient);
+        assert_eq!(
+            kind("failed to query http://1.2.3.4:4010: timed out"),
+            ErrorKind::Transient
+        );
+        assert_eq!(
+            kind("No zone found for domain: example.com"),
+            ErrorKind::Other
+        );
+        assert_eq!(kind("Porkbun API error 429: slow down"), ErrorKind::Other);
+        assert_eq!(
+            kind("Gandi API error (403 Forbidden): no access"),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            kind("Failed to obtain gcloud access token (502 Bad Gateway): upstream"),
+            ErrorKind::Transient
+        );
+        // 错误格式之外的数字不视为状态码
+        assert_eq!(
+            kind("No zone found for domain: 403.example.com"),
+            ErrorKind::Other
+        );
+        assert_eq!(kind("record 503 not found"), ErrorKind::Other);
+        assert_eq!(
+            kind("DigitalOcean API error (404 Not Found): record 401 missing"),
+            ErrorKind::Other
+        );
+        let io_error = Error::new(io::Error::from(io::ErrorKind::WouldBlock));
+        assert_eq!(
+            classify(&io_error.context("no response from gateway")),
+            ErrorKind::Transient
+        );
+
+        // 临时性错误立即重试，其他错误直接返回
+        let config = RetryConfig {
+            transient_retry_delay: Duration::from_millis(1),
+            ..RetryConfig::default()
+        };
+        let mut calls = 0;
+        let result = config.retry_transient(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(anyhow!("connection refused"))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        let mut calls = 0;
+        let result: Result<(), Error> = config.retry_transient(|| {
+            calls += 1;
+            Err(anyhow!("401 Unauthorized"))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        // 退避间隔翻倍并带有抖动，不超过上限，不小于检查间隔
+        let interval = Duration::from_secs(60);
+        let delay = config.backoff(interval, 2);
+        assert!(delay >= Duration::from_secs(192) && delay < Duration::from_secs(288));
+        assert!(config.backoff(interval, 40) <= config.max_backoff.mul_f64(1.2));
+        assert_eq!(
+            config.backoff(Duration::from_secs(3600), 1),
+            Duration::from_secs(3600)
+        );
+    }
+}
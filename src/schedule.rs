@@ -0,0 +1,297 @@
+use anyhow::{Error, anyhow};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, TimeZone, Timelike,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// ========== 时长 ==========
+
+/// 解析时长，支持 "30s"、"5m"、"2h"、"1d" 及其组合 (如 "1h30m")，不带单位的数字表示秒
+pub fn parse_duration(text: &str) -> Result<Duration, Error> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let invalid =
+        || anyhow!("invalid duration {text:?}, expected a value like \"30s\", \"5m\" or \"1h30m\"");
+    let mut total = 0u64;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// 配置中的时长，可以写作秒数 (如 300) 或带单位的字符串 (如 "5m")
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DurationValue {
+    Secs(u64),
+    Text(String),
+}
+
+impl DurationValue {
+    fn into_duration<E: serde::de::Error>(self) -> Result<Duration, E> {
+        match self {
+            DurationValue::Secs(secs) => Ok(Duration::from_secs(secs)),
+            DurationValue::Text(text) => parse_duration(&text).map_err(E::custom),
+        }
+    }
+}
+
+/// 反序列化时长，配置中可以写作秒数 (如 300) 或带单位的字符串 (如 "5m")
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    DurationValue::deserialize(deserializer)?.into_duration()
+}
+
+/// 反序列化可选的时长
+pub fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<DurationValue>::deserialize(deserializer)?
+        .map(DurationValue::into_duration)
+        .transpose()
+}
+
+// ========== 类cron的检查时段 ==========
+
+/// 类cron的时段表达式 "分 时 日 月 周"，当前时间匹配时允许检查
+///
+/// 每个字段支持 `*`、数字、范围 `a-b`、步长 `*/n` 或 `a-b/n` 以及逗号分隔的列表，
+/// 周的取值为0-7 (0和7都表示周日)；与cron相同，日和周都不是 `*` 时满足其一即可
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct CronWindow {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// 日和周字段是否不为 `*`
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronWindow {
+    /// 给定时间是否在时段内，精确到分钟
+    pub fn matches<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> bool {
+        bit(self.minutes, time.minute()) && bit(self.hours, time.hour()) && self.matches_date(time)
+    }
+
+    fn matches_date<T: Datelike>(&self, date: &T) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// 解析一个字段，返回取值的位图
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, Error> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&step| step > 0)),
+            None => (part, Some(1)),
+        };
+        let step = step.ok_or_else(|| anyhow!("invalid step in {part:?}"))?;
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                // "a/n" 表示从a开始到最大值
+                None if part.contains('/') => (range.parse()?, max),
+                None => {
+                    let value = range.parse()?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("{part:?} is out of range {min}-{max}"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for CronWindow {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self, Error> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(anyhow!(
+                "invalid window {expression:?}, expected 5 fields \"minute hour day month weekday\""
+            ));
+        };
+        let parse = |field: &str, min, max| {
+            parse_field(field, min, max).map_err(|e| anyhow!("invalid window {expression:?}: {e}"))
+        };
+        let mut weekdays_mask = parse(weekdays, 0, 7)?;
+        // 7 与 0 都表示周日
+        if weekdays_mask & (1 << 7) != 0 {
+            weekdays_mask |= 1;
+        }
+        Ok(CronWindow {
+            expression: expression.to_string(),
+            minutes: parse(minutes, 0, 59)?,
+            hours: parse(hours, 0, 23)?,
+            days: parse(days, 1, 31)?,
+            months: parse(months, 1, 12)?,
+            weekdays: weekdays_mask,
+            days_restricted: days != "*",
+            weekdays_restricted: weekdays != "*",
+        })
+    }
+}
+
+impl TryFrom<String> for CronWindow {
+    type Error = Error;
+
+    fn try_from(expression: String) -> Result<Self, Error> {
+        expression.parse()
+    }
+}
+
+impl From<CronWindow> for String {
+    fn from(window: CronWindow) -> String {
+        window.expression
+    }
+}
+
+impl fmt::Display for CronWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// 查找不早于from的第一个处于任一时段内的时间 (精确到分钟)，未设置时段时即为from
+///
+/// 最多向后查找4年 (覆盖2月29日)，时段永远不会匹配时返回None
+pub fn next_in_windows(windows: &[CronWindow], from: DateTime<Local>) -> Option<DateTime<Local>> {
+    if windows.is_empty() || windows.iter().any(|window| window.matches(&from)) {
+        return Some(from);
+    }
+    let start = from.date_naive();
+    (0..4 * 366)
+        .filter_map(|offset| start.checked_add_signed(ChronoDuration::days(offset)))
+        .filter(|date| windows.iter().any(|window| window.matches_date(date)))
+        .find_map(|date| first_minute(windows, date, from))
+}
+
+/// 某一天中晚于from的第一个匹配的分钟
+fn first_minute(
+    windows: &[CronWindow],
+    date: NaiveDate,
+    from: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    (0..24 * 60).find_map(|minute| {
+        let time = date.and_hms_opt(minute / 60, minute % 60, 0)?;
+        // 夏令时切换时不存在的时间跳过，重复的时间取较早的一个
+        let time = Local.from_local_datetime(&time).earliest()?;
+        (time > from && windows.iter().any(|window| window.matches(&time))).then_some(time)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        for invalid in ["", "m", "5x", "1h30", "-5s"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+
+        // 工作日的8:00-18:59，每10分钟
+        let window: CronWindow = "*/10 8-18 * * 1-5".parse().unwrap();
+        let at = |text: &str| {
+            Local
+                .from_local_datetime(&text.parse().unwrap())
+                .earliest()
+                .unwrap()
+        };
+        // 2024-06-03 为周一
+        assert!(window.matches(&at("2024-06-03T08:00:00")));
+        assert!(window.matches(&at("2024-06-03T18:50:00")));
+        assert!(!window.matches(&at("2024-06-03T08:05:00")));
+        assert!(!window.matches(&at("2024-06-03T19:00:00")));
+        assert!(!window.matches(&at("2024-06-08T10:00:00")));
+        assert_eq!(
+            next_in_windows(std::slice::from_ref(&window), at("2024-06-07T19:30:00")),
+            Some(at("2024-06-10T08:00:00"))
+        );
+        assert_eq!(
+            next_in_windows(std::slice::from_ref(&window), at("2024-06-03T08:01:00")),
+            Some(at("2024-06-03T08:10:00"))
+        );
+
+        // 日和周都有限制时满足其一即可，7表示周日
+        let window: CronWindow = "0 3 1 * 7".parse().unwrap();
+        assert!(window.matches(&at("2024-06-01T03:00:00")));
+        assert!(window.matches(&at("2024-06-02T03:00:00")));
+        assert!(!window.matches(&at("2024-06-03T03:00:00")));
+
+        // 2月29日在4年内可以找到，2月30日永远不会匹配
+        let leap: CronWindow = "0 0 29 2 *".parse().unwrap();
+        assert_eq!(
+            next_in_windows(&[leap], at("2024-03-01T00:00:00")),
+            Some(at("2028-02-29T00:00:00"))
+        );
+        let never: CronWindow = "0 0 30 2 *".parse().unwrap();
+        assert_eq!(next_in_windows(&[never], at("2024-03-01T00:00:00")), None);
+
+        for invalid in [
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(invalid.parse::<CronWindow>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Error, anyhow};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::file::write_atomic;
+
+// ========== 持久化状态 ==========
+
+/// 保存在状态文件中的运行状态，重启后恢复，避免重新查询全部记录
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct State {
+    /// 每个更新目标的状态，key为 "域名/记录类型/provider#序号"
+    pub targets: BTreeMap<String, TargetState>,
+    /// 每个域名配置的调度状态，key为域名配置的名称
+    pub domains: BTreeMap<String, DomainState>,
+    /// 每个完整域名各类型记录最近一次检测到应当指向的IP，key为 "域名/记录类型"，用于记录IP变化历史
+    pub detected_ips: BTreeMap<String, String>,
+}
+
+/// 单个更新目标的状态
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TargetState {
+    /// 最近一次成功同步的IP
+    pub last_ip: String,
+    /// provider中记录的ID (如有)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<String>,
+    /// 最近一次更新成功的时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<DateTime<Local>>,
+    /// 最近一次更新失败的时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<DateTime<Local>>,
+    /// 最近一次更新失败的错误信息，成功后清空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// 连续失败的次数，成功后清零
+    pub failures: u32,
+}
+
+/// 单个域名配置的调度状态
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DomainState {
+    /// 最近一次强制从provider获取记录的时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_force_refresh: Option<DateTime<Local>>,
+    /// 连续检查失败的次数
+    pub failures: u32,
+    /// 连续认证失败的次数
+    pub auth_failures: u32,
+    /// 是否因认证失败而暂停
+    pub paused: bool,
+    /// 失败后退避或暂停到的时间，重启后在此之前不检查
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<DateTime<Local>>,
+}
+
+impl State {
+    /// 读取状态文件，文件不存在时返回空状态
+    pub fn load(path: &Path) -> Result<State, Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(State::default()),
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// 写入状态文件，先写临时文件再rename，进程中途退出时不会留下写了一半的文件
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(self)?;
+        write_atomic(path, &content)
+    }
+
+    /// 删除不在配置中的目标、域名配置和记录的状态
+    pub fn retain(
+        &mut self,
+        target_keys: &[String],
+        domain_keys: &[String],
+        record_keys: &[String],
+    ) {
+        self.targets.retain(|key, _| target_keys.contains(key));
+        self.domains.retain(|key, _| domain_keys.contains(key));
+        self.detected_ips.retain(|key, _| record_keys.contains(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state() {
+        let dir = std::env::temp_dir().join(format!("ddns-state-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        let _ = fs::remove_file(&path);
+
+        // 文件不存在时为空状态
+        assert_eq!(State::load(&path).unwrap(), State::default());
+
+        let mut state = State::default();
+        state.targets.insert(
+            "home.example.com/A/cloudflare#0".to_string(),
+            TargetState {
+                last_ip: "1.2.3.4".to_string(),
+                record_id: Some("abc".to_string()),
+                last_success: Some(Local::now()),
+                ..TargetState::default()
+            },
+        );
+        state.targets.insert(
+            "old.example.com/A/cloudflare#0".to_string(),
+            TargetState::default(),
+        );
+        state.domains.insert(
+            "home.example.com".to_string(),
+            DomainState {
+                failures: 2,
+                retry_at: Some(Local::now()),
+                ..DomainState::default()
+            },
+        );
+        state.detected_ips.insert(
+            "home.example.com/AAAA".to_string(),
+            "2001:db8::1".to_string(),
+        );
+        state
+            .detected_ips
+            .insert("old.example.com/A".to_string(), "1.2.3.4".to_string());
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path).unwrap(), state);
+        assert!(!dir.join(".state.json.ddns.tmp").exists());
+
+        state.retain(
+            &["home.example.com/A/cloudflare#0".to_string()],
+            &["home.example.com".to_string()],
+            &["home.example.com/AAAA".to_string()],
+        );
+        assert_eq!(state.targets.len(), 1);
+        assert_eq!(state.domains.len(), 1);
+        assert_eq!(state.detected_ips.len(), 1);
+
+        fs::write(&path, "not json").unwrap();
+        assert!(State::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread::{self, JoinHandle};
+
+// ========== 测试用的辅助函数 ==========
+
+/// 本地模拟的HTTP服务：依次对每个连接返回bodies中的响应体，结束后返回收到的 (请求头, 请求体)
+///
+/// 全部响应返回后停止监听，之后的请求连接失败
+pub(crate) fn serve_http<B: Into<String>>(
+    bodies: Vec<B>,
+) -> (SocketAddr, JoinHandle<Vec<(String, String)>>) {
+    let bodies: Vec<String> = bodies.into_iter().map(Into::into).collect();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for body in bodies {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let mut request_body = vec![0u8; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            requests.push((head, String::from_utf8(request_body).unwrap()));
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (address, handle)
+}